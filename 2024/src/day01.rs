@@ -0,0 +1,628 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::Add;
+use std::str::FromStr;
+
+use num_bigint::{BigInt, BigUint};
+use num_traits::CheckedAdd;
+
+use crate::{Accumulation, ArithmeticError};
+
+#[derive(Debug, PartialEq)]
+pub enum PairingError {
+    LengthMismatch { left: usize, right: usize },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ColumnError {
+    InvalidValue {
+        line: usize,
+        value: String,
+    },
+    InconsistentWidth {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    MissingColumn {
+        column: usize,
+    },
+    Arithmetic(ArithmeticError),
+}
+
+impl From<ArithmeticError> for ColumnError {
+    fn from(error: ArithmeticError) -> ColumnError {
+        ColumnError::Arithmetic(error)
+    }
+}
+
+// Scores are accumulated as big integers, and only have to fit at the end
+pub trait LocationId:
+    Clone + Ord + Hash + FromStr + Default + Into<BigInt> + TryFrom<BigInt>
+{
+    // Wide enough for any difference, like the unsigned result of `abs_diff`
+    type Distance: Clone + Default + Add<Output = Self::Distance> + CheckedAdd + Into<BigInt>;
+
+    fn distance(&self, other: &Self) -> Self::Distance;
+}
+
+macro_rules! impl_location_id {
+    ($($id:ty => $distance:ty),*) => {
+        $(
+            impl LocationId for $id {
+                type Distance = $distance;
+
+                fn distance(&self, other: &Self) -> $distance {
+                    self.abs_diff(*other)
+                }
+            }
+        )*
+    };
+}
+
+impl_location_id!(
+    u8 => u8, u16 => u16, u32 => u32, u64 => u64, u128 => u128, usize => usize,
+    i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize
+);
+
+impl LocationId for BigInt {
+    type Distance = BigUint;
+
+    fn distance(&self, other: &Self) -> BigUint {
+        (self - other).magnitude().clone()
+    }
+}
+
+fn sorted_distances<T: LocationId>(left: &Vec<T>, right: &Vec<T>) -> Vec<T::Distance> {
+    if left.len() != right.len() {
+        panic!("Vectors must be the same length");
+    }
+
+    let mut sorted_left = left.clone();
+    let mut sorted_right = right.clone();
+    sorted_left.sort();
+    sorted_right.sort();
+
+    return sorted_left
+        .iter()
+        .zip(sorted_right.iter())
+        .map(|(l, r)| l.distance(r))
+        .collect();
+}
+
+pub fn total_distance<T: LocationId>(left: &Vec<T>, right: &Vec<T>) -> T::Distance {
+    let mut distance = T::Distance::default();
+    for pair_distance in sorted_distances(left, right) {
+        distance = distance + pair_distance;
+    }
+
+    return distance;
+}
+
+pub fn total_distance_checked<T: LocationId>(
+    left: &Vec<T>,
+    right: &Vec<T>,
+) -> Result<T::Distance, ArithmeticError> {
+    return sorted_distances(left, right)
+        .iter()
+        .try_fold(T::Distance::default(), |acc, d| acc.checked_add(d))
+        .ok_or(ArithmeticError::Overflow);
+}
+
+pub fn total_distance_with<T: LocationId>(
+    left: &Vec<T>,
+    right: &Vec<T>,
+    accumulation: Accumulation,
+) -> Result<BigInt, ArithmeticError> {
+    return match accumulation {
+        Accumulation::Checked => total_distance_checked(left, right).map(|d| d.into()),
+        Accumulation::ArbitraryPrecision => Ok(sorted_distances(left, right)
+            .into_iter()
+            .map(|d| d.into())
+            .sum()),
+    };
+}
+
+pub fn parse_columns<T: LocationId>(contents: &str) -> Result<Vec<Vec<T>>, ColumnError> {
+    let mut columns: Vec<Vec<T>> = Vec::new();
+    // Line numbers are 1-based, and blank lines are skipped
+    for (line, line_no) in contents.lines().zip(1..) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+        if columns.is_empty() {
+            columns = vec![Vec::new(); fields.len()];
+        } else if fields.len() != columns.len() {
+            return Err(ColumnError::InconsistentWidth {
+                line: line_no,
+                expected: columns.len(),
+                found: fields.len(),
+            });
+        }
+        for (column, field) in columns.iter_mut().zip(fields) {
+            column.push(field.parse::<T>().map_err(|_| ColumnError::InvalidValue {
+                line: line_no,
+                value: field.to_string(),
+            })?);
+        }
+    }
+    return Ok(columns);
+}
+
+fn column_pair<T>(
+    columns: &Vec<Vec<T>>,
+    a: usize,
+    b: usize,
+) -> Result<(&Vec<T>, &Vec<T>), ColumnError> {
+    let column = |index: usize| {
+        columns
+            .get(index)
+            .ok_or(ColumnError::MissingColumn { column: index })
+    };
+    return Ok((column(a)?, column(b)?));
+}
+
+pub fn columns_total_distance<T: LocationId>(
+    columns: &Vec<Vec<T>>,
+    a: usize,
+    b: usize,
+) -> Result<T::Distance, ColumnError> {
+    // All columns have the same length, as checked when parsing
+    let (left, right) = column_pair(columns, a, b)?;
+    return Ok(total_distance(left, right));
+}
+
+pub fn columns_similarity_score<T: LocationId>(
+    columns: &Vec<Vec<T>>,
+    a: usize,
+    b: usize,
+) -> Result<T, ColumnError> {
+    let (left, right) = column_pair(columns, a, b)?;
+    return Ok(similarity_score(left, right)?);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LengthMismatch {
+    Error,
+    // Drop the largest leftover values
+    Truncate,
+    // Pair the leftover values with zero
+    PadWithZero,
+    // Drop whichever leftover values give the smallest total distance
+    MinimizeDistance,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pair<T: LocationId> {
+    pub left: T,
+    pub right: T,
+    pub distance: T::Distance,
+}
+
+impl<T: LocationId> Pair<T> {
+    fn new(left: T, right: T) -> Pair<T> {
+        let distance = left.distance(&right);
+        Pair {
+            left,
+            right,
+            distance,
+        }
+    }
+}
+
+fn pair_minimizing_distance<T: LocationId>(shorter: &[T], longer: &[T]) -> Vec<(T, T)> {
+    // Both are sorted, so the optimal pairs keep their order and we only choose
+    // which values of the longer list to skip: cost[ii][jj] pairs the first ii
+    // values of the shorter list among the first jj values of the longer one.
+    // Costs are big integers, so that they cannot overflow
+    let mut cost = vec![vec![BigInt::default(); longer.len() + 1]; shorter.len() + 1];
+    for ii in 1..=shorter.len() {
+        for jj in ii..=longer.len() {
+            let distance: BigInt = shorter[ii - 1].distance(&longer[jj - 1]).into();
+            let take = &cost[ii - 1][jj - 1] + distance;
+            // With as many values left in both lists, none can be skipped
+            cost[ii][jj] = if jj > ii {
+                take.min(cost[ii][jj - 1].clone())
+            } else {
+                take
+            };
+        }
+    }
+
+    let mut pairs: Vec<(T, T)> = Vec::new();
+    let (mut ii, mut jj) = (shorter.len(), longer.len());
+    while ii > 0 {
+        if (jj > ii) && (cost[ii][jj] == cost[ii][jj - 1]) {
+            jj -= 1;
+        } else {
+            pairs.push((shorter[ii - 1].clone(), longer[jj - 1].clone()));
+            ii -= 1;
+            jj -= 1;
+        }
+    }
+    pairs.reverse();
+    return pairs;
+}
+
+pub fn pair_lists<T: LocationId>(
+    left: &Vec<T>,
+    right: &Vec<T>,
+    mismatch: LengthMismatch,
+) -> Result<Vec<Pair<T>>, PairingError> {
+    let mut sorted_left = left.clone();
+    let mut sorted_right = right.clone();
+    sorted_left.sort();
+    sorted_right.sort();
+
+    let common_len = left.len().min(right.len());
+    let mut pairs: Vec<Pair<T>> = sorted_left
+        .iter()
+        .zip(sorted_right.iter())
+        .map(|(l, r)| Pair::new(l.clone(), r.clone()))
+        .collect();
+    if left.len() == right.len() {
+        return Ok(pairs);
+    }
+
+    match mismatch {
+        LengthMismatch::Error => {
+            return Err(PairingError::LengthMismatch {
+                left: left.len(),
+                right: right.len(),
+            });
+        }
+        LengthMismatch::Truncate => (),
+        LengthMismatch::PadWithZero => {
+            pairs.extend(
+                sorted_left
+                    .iter()
+                    .skip(common_len)
+                    .map(|l| Pair::new(l.clone(), T::default())),
+            );
+            pairs.extend(
+                sorted_right
+                    .iter()
+                    .skip(common_len)
+                    .map(|r| Pair::new(T::default(), r.clone())),
+            );
+        }
+        LengthMismatch::MinimizeDistance => {
+            pairs = if left.len() < right.len() {
+                pair_minimizing_distance(&sorted_left, &sorted_right)
+                    .into_iter()
+                    .map(|(l, r)| Pair::new(l, r))
+                    .collect()
+            } else {
+                pair_minimizing_distance(&sorted_right, &sorted_left)
+                    .into_iter()
+                    .map(|(r, l)| Pair::new(l, r))
+                    .collect()
+            };
+        }
+    }
+    return Ok(pairs);
+}
+
+pub fn similarity_score<T: LocationId>(
+    left: &Vec<T>,
+    right: &Vec<T>,
+) -> Result<T, ArithmeticError> {
+    return similarity_score_iter(left, right);
+}
+
+pub fn similarity_score_iter<T, L, R>(left: L, right: R) -> Result<T, ArithmeticError>
+where
+    T: LocationId,
+    L: IntoIterator,
+    L::Item: Borrow<T>,
+    R: IntoIterator,
+    R::Item: Borrow<T>,
+{
+    // Count the right column once, then the left one can be streamed
+    let mut right_counts: HashMap<T, usize> = HashMap::new();
+    for right_element in right {
+        *right_counts
+            .entry(right_element.borrow().clone())
+            .or_default() += 1;
+    }
+
+    let mut score = BigInt::default();
+    for left_element in left {
+        let left_element = left_element.borrow();
+        let num_times = right_counts.get(left_element).copied().unwrap_or(0);
+        let value: BigInt = left_element.clone().into();
+        score += value * num_times;
+    }
+    return T::try_from(score).map_err(|_| ArithmeticError::Overflow);
+}
+
+pub fn parse(contents: String) -> (Vec<usize>, Vec<usize>) {
+    return parse_generic(contents);
+}
+
+pub fn parse_generic<T: LocationId>(contents: String) -> (Vec<T>, Vec<T>) {
+    let mut columns = parse_columns(&contents).expect("Input should have valid columns");
+    // Without any rows there are no columns either, but both lists are just empty
+    if columns.is_empty() {
+        return (Vec::new(), Vec::new());
+    }
+    if columns.len() < 2 {
+        panic!("Input should have two columns");
+    }
+    let right = columns.remove(1);
+    let left = columns.remove(0);
+
+    return (left, right);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_works() {
+        let (left, right) = parse(String::from("3   4\n4   3\n2   5\n"));
+        assert_eq!(left, vec![3, 4, 2]);
+        assert_eq!(right, vec![4, 3, 5]);
+
+        let (left, right) = parse_generic::<i8>(String::from("-3   4\n"));
+        assert_eq!(left, vec![-3]);
+        assert_eq!(right, vec![4]);
+
+        let (left, right) = parse(String::new());
+        assert!(left.is_empty() && right.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "two columns")]
+    fn parse_single_column_fails() {
+        let _ = parse(String::from("3\n4\n"));
+    }
+
+    #[test]
+    fn total_distance_works() {
+        let left: Vec<usize> = vec![3, 4, 2, 1, 3, 3];
+        let right: Vec<usize> = vec![4, 3, 5, 3, 9, 3];
+
+        let result = total_distance(&left, &right);
+        assert_eq!(result, 11);
+    }
+
+    #[test]
+    fn total_distance_with_works() {
+        let left: Vec<usize> = vec![3, 4, 2, 1, 3, 3];
+        let right: Vec<usize> = vec![4, 3, 5, 3, 9, 3];
+
+        for accumulation in [Accumulation::Checked, Accumulation::ArbitraryPrecision] {
+            let result = total_distance_with(&left, &right, accumulation);
+            assert_eq!(result, Ok(BigInt::from(11)));
+        }
+    }
+
+    #[test]
+    fn total_distance_with_overflow_works() {
+        let left = vec![0, 0, 0];
+        let right = vec![usize::MAX, usize::MAX, usize::MAX];
+
+        assert_eq!(
+            total_distance_with(&left, &right, Accumulation::Checked),
+            Err(ArithmeticError::Overflow)
+        );
+        assert_eq!(
+            total_distance_with(&left, &right, Accumulation::ArbitraryPrecision),
+            Ok(BigInt::from(usize::MAX) * 3)
+        );
+
+        let left: Vec<i8> = vec![i8::MIN, i8::MIN];
+        let right: Vec<i8> = vec![i8::MAX, 0];
+        assert_eq!(
+            total_distance_checked(&left, &right),
+            Err(ArithmeticError::Overflow)
+        );
+        assert_eq!(
+            total_distance_with(&left, &right, Accumulation::Checked),
+            Err(ArithmeticError::Overflow)
+        );
+        assert_eq!(
+            total_distance_checked(&vec![i8::MIN], &vec![i8::MAX]),
+            Ok(u8::MAX)
+        );
+        assert_eq!(
+            total_distance_with(&left, &right, Accumulation::ArbitraryPrecision),
+            Ok(BigInt::from(255 + 128))
+        );
+    }
+
+    #[test]
+    fn pair_lists_works() {
+        let left: Vec<usize> = vec![3, 4, 2, 1, 3, 3];
+        let right: Vec<usize> = vec![4, 3, 5, 3, 9, 3];
+
+        let pairs = pair_lists(&left, &right, LengthMismatch::Error).unwrap();
+        assert_eq!(
+            pairs[0],
+            Pair {
+                left: 1,
+                right: 3,
+                distance: 2
+            }
+        );
+        assert_eq!(pairs.iter().map(|p| p.distance).sum::<usize>(), 11);
+    }
+
+    #[test]
+    fn pair_lists_generic_works() {
+        let left: Vec<i8> = vec![-100, 100, 0];
+        let right: Vec<i8> = vec![100, -100];
+
+        assert_eq!(
+            pair_lists(&left, &right, LengthMismatch::PadWithZero).unwrap(),
+            vec![Pair::new(-100, -100), Pair::new(0, 100), Pair::new(100, 0)]
+        );
+        assert_eq!(
+            pair_lists(&left, &right, LengthMismatch::MinimizeDistance).unwrap(),
+            vec![Pair::new(-100, -100), Pair::new(100, 100)]
+        );
+        assert_eq!(
+            pair_lists(&vec![i8::MIN], &vec![i8::MAX], LengthMismatch::Error).unwrap()[0].distance,
+            u8::MAX
+        );
+
+        let left: Vec<BigInt> = vec![BigInt::from(-5), BigInt::from(7)];
+        let right: Vec<BigInt> = vec![BigInt::from(3)];
+        assert_eq!(
+            pair_lists(&left, &right, LengthMismatch::MinimizeDistance).unwrap(),
+            vec![Pair::new(BigInt::from(7), BigInt::from(3))]
+        );
+    }
+
+    #[test]
+    fn pair_lists_with_length_mismatch_works() {
+        let left: Vec<usize> = vec![10, 1, 2];
+        let right: Vec<usize> = vec![11, 1];
+
+        assert_eq!(
+            pair_lists(&left, &right, LengthMismatch::Error),
+            Err(PairingError::LengthMismatch { left: 3, right: 2 })
+        );
+        assert_eq!(
+            pair_lists(&left, &right, LengthMismatch::Truncate).unwrap(),
+            vec![Pair::new(1, 1), Pair::new(2, 11)]
+        );
+        assert_eq!(
+            pair_lists(&left, &right, LengthMismatch::PadWithZero).unwrap(),
+            vec![Pair::new(1, 1), Pair::new(2, 11), Pair::new(10, 0)]
+        );
+        assert_eq!(
+            pair_lists(&left, &right, LengthMismatch::MinimizeDistance).unwrap(),
+            vec![Pair::new(1, 1), Pair::new(10, 11)]
+        );
+        assert_eq!(
+            pair_lists(&right, &left, LengthMismatch::MinimizeDistance).unwrap(),
+            vec![Pair::new(1, 1), Pair::new(11, 10)]
+        );
+    }
+
+    #[test]
+    fn parse_columns_works() {
+        let contents = "3   4   1\n4   3   1\n2   5   2\n\n1   3   3\n3   9   4\n3   3   5\n";
+        let columns = parse_columns::<usize>(contents).unwrap();
+
+        assert_eq!(columns.len(), 3);
+        assert_eq!(columns[0], vec![3, 4, 2, 1, 3, 3]);
+        assert_eq!(columns_total_distance(&columns, 0, 1), Ok(11));
+        assert_eq!(columns_similarity_score(&columns, 0, 1), Ok(31));
+        assert_eq!(columns_total_distance(&columns, 2, 0), Ok(4));
+        assert_eq!(columns_similarity_score(&columns, 0, 2), Ok(17));
+        assert_eq!(
+            columns_total_distance(&columns, 0, 3),
+            Err(ColumnError::MissingColumn { column: 3 })
+        );
+    }
+
+    #[test]
+    fn parse_columns_errors() {
+        assert_eq!(
+            parse_columns::<usize>("3   4\n4   3   1\n"),
+            Err(ColumnError::InconsistentWidth {
+                line: 2,
+                expected: 2,
+                found: 3
+            })
+        );
+        assert_eq!(
+            parse_columns::<usize>("3   4\n4   -3\n"),
+            Err(ColumnError::InvalidValue {
+                line: 2,
+                value: "-3".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn total_distance_generic_works() {
+        let left: Vec<i64> = vec![-3, 4, 2, -1, 3, 3];
+        let right: Vec<i64> = vec![4, 3, -5, 3, 9, 3];
+        assert_eq!(total_distance(&left, &right), 13);
+        assert_eq!(similarity_score(&left, &right), Ok(22));
+
+        let left: Vec<i8> = vec![i8::MIN];
+        let right: Vec<i8> = vec![i8::MAX];
+        assert_eq!(total_distance(&left, &right), u8::MAX);
+
+        let columns = parse_columns::<u128>("340282366920938463463374607431768211455 1\n").unwrap();
+        assert_eq!(columns_total_distance(&columns, 0, 1), Ok(u128::MAX - 1));
+
+        let contents = "-100000000000000000000000000000000000000000 7\n3 -7\n";
+        let columns = parse_columns::<BigInt>(contents).unwrap();
+        assert_eq!(
+            columns_total_distance(&columns, 0, 1),
+            Ok("100000000000000000000000000000000000000000"
+                .parse::<BigUint>()
+                .unwrap()
+                - 3u32)
+        );
+        assert_eq!(
+            columns_similarity_score(&columns, 0, 1),
+            Ok(BigInt::from(0))
+        );
+    }
+
+    #[test]
+    fn similarity_score_works() {
+        let left = vec![3, 4, 2, 1, 3, 3];
+        let right = vec![4, 3, 5, 3, 9, 3];
+
+        let result = similarity_score(&left, &right);
+        assert_eq!(result, Ok(31));
+    }
+
+    #[test]
+    fn similarity_score_overflow_works() {
+        assert_eq!(
+            similarity_score::<i16>(&vec![1], &vec![1; 65541]),
+            Err(ArithmeticError::Overflow)
+        );
+        assert_eq!(
+            similarity_score::<i8>(&vec![-1], &vec![-1; 128]),
+            Ok(i8::MIN)
+        );
+        assert_eq!(
+            similarity_score::<u8>(&vec![200, 200], &vec![200]),
+            Err(ArithmeticError::Overflow)
+        );
+
+        let columns = parse_columns::<u8>("200 200\n200 1\n").unwrap();
+        assert_eq!(
+            columns_similarity_score(&columns, 0, 1),
+            Err(ColumnError::Arithmetic(ArithmeticError::Overflow))
+        );
+    }
+
+    #[test]
+    fn similarity_score_iter_works() {
+        let left = [3, 4, 2, 1, 3, 3];
+        let right = [4, 3, 5, 3, 9, 3];
+
+        assert_eq!(similarity_score_iter::<usize, _, _>(&left, &right), Ok(31));
+        assert_eq!(
+            similarity_score_iter::<usize, _, _>(left, right.iter()),
+            Ok(31)
+        );
+        assert_eq!(
+            similarity_score_iter::<usize, _, _>(
+                left.iter().copied(),
+                "4 3 5 3 9 3"
+                    .split(' ')
+                    .map(|n| n.parse::<usize>().unwrap())
+            ),
+            Ok(31)
+        );
+        assert_eq!(
+            similarity_score_iter::<usize, _, _>(&left[..0], &right),
+            Ok(0)
+        );
+    }
+}
@@ -0,0 +1,679 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub enum InvalidReportError {
+    CannotDampen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trend {
+    Increasing,
+    Decreasing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SafetyPolicy {
+    // Allowed size of the steps between levels, in absolute value
+    pub min_step: isize,
+    pub max_step: isize,
+    // Whether equal consecutive levels make the report unsafe
+    pub strict: bool,
+    // Trend that safe reports must follow, or either if `None`
+    pub direction: Option<Trend>,
+}
+
+impl Default for SafetyPolicy {
+    fn default() -> SafetyPolicy {
+        SafetyPolicy {
+            min_step: 1,
+            max_step: 3,
+            strict: true,
+            direction: None,
+        }
+    }
+}
+
+impl SafetyPolicy {
+    fn directions(&self) -> Vec<bool> {
+        // As `increasing` flags, to try one after the other
+        return match self.direction {
+            Some(Trend::Increasing) => vec![true],
+            Some(Trend::Decreasing) => vec![false],
+            None => vec![true, false],
+        };
+    }
+
+    fn is_safe_step(&self, from: isize, to: isize, increasing: bool) -> bool {
+        let step = if increasing { to - from } else { from - to };
+        if step == 0 {
+            return !self.strict;
+        }
+        return (step > 0) & (self.min_step..=self.max_step).contains(&step);
+    }
+}
+
+fn report_diffs(report: &Vec<isize>) -> Vec<isize> {
+    let mut report_orig = report.clone();
+    let mut report_shifted = report.clone();
+
+    // TODO: Use slices instead?
+    report_orig.remove(report_orig.len() - 1);
+    report_shifted.remove(0);
+
+    return report_orig
+        .iter()
+        .zip(report_shifted.iter())
+        .map(|(&o, &s)| o - s)
+        .collect();
+}
+
+fn report_safety_no_dampener(report: &Vec<isize>, policy: &SafetyPolicy) -> bool {
+    let diffs = report_diffs(report);
+
+    // Differences are computed backwards, hence the negative steps
+    return policy.directions().into_iter().any(|increasing| {
+        diffs
+            .iter()
+            .all(|&d| policy.is_safe_step(0, -d, increasing))
+    });
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Dampening {
+    pub report: Vec<isize>,
+    // Indices of the levels removed from the original report
+    pub removed: Vec<usize>,
+}
+
+fn is_safe_without(
+    report: &Vec<isize>,
+    removed: usize,
+    increasing: bool,
+    policy: &SafetyPolicy,
+) -> bool {
+    let kept: Vec<isize> = report
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| index != removed)
+        .map(|(_, &level)| level)
+        .collect();
+    return kept
+        .windows(2)
+        .all(|w| policy.is_safe_step(w[0], w[1], increasing));
+}
+
+fn dampen_single_level(report: &Vec<isize>, policy: &SafetyPolicy) -> Option<usize> {
+    // For a given direction, one of the two levels of the first unsafe step
+    // has to go, so there are at most four candidates to check in linear time
+    let mut candidates: Vec<(usize, bool)> = Vec::new();
+    for increasing in policy.directions() {
+        if let Some(index) = report
+            .windows(2)
+            .position(|w| !policy.is_safe_step(w[0], w[1], increasing))
+        {
+            candidates.push((index, increasing));
+            candidates.push((index + 1, increasing));
+        }
+    }
+    candidates.sort();
+    return candidates
+        .into_iter()
+        .find(|&(index, increasing)| is_safe_without(report, index, increasing, policy))
+        .map(|(index, _)| index);
+}
+
+fn dampen_levels(
+    report: &Vec<isize>,
+    max_removals: usize,
+    increasing: bool,
+    policy: &SafetyPolicy,
+) -> Option<Vec<usize>> {
+    // Fewest removals so that the kept levels end at each index,
+    // only looking back as far as the removals allow
+    let mut removals: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = Vec::new();
+    for index in 0..report.len() {
+        let mut best = (index, None);
+        for prev in index.saturating_sub(max_removals + 1)..index {
+            let cost = removals[prev] + (index - prev - 1);
+            if (cost < best.0) & policy.is_safe_step(report[prev], report[index], increasing) {
+                best = (cost, Some(prev));
+            }
+        }
+        removals.push(best.0);
+        previous.push(best.1);
+    }
+
+    let last = (0..report.len())
+        .filter(|&index| removals[index] + (report.len() - 1 - index) <= max_removals)
+        .min_by_key(|&index| removals[index] + (report.len() - 1 - index))?;
+    let mut kept: Vec<usize> = vec![last];
+    while let Some(prev) = previous[*kept.last().unwrap()] {
+        kept.push(prev);
+    }
+    return Some(
+        (0..report.len())
+            .filter(|index| !kept.contains(index))
+            .collect(),
+    );
+}
+
+pub fn dampen_report_with_tolerance(
+    report: &Vec<isize>,
+    max_removals: usize,
+    policy: &SafetyPolicy,
+) -> Result<Dampening, InvalidReportError> {
+    let removed: Vec<usize> = if report_safety_no_dampener(report, policy) {
+        Vec::new()
+    } else {
+        match max_removals {
+            0 => return Err(InvalidReportError::CannotDampen),
+            1 => vec![dampen_single_level(report, policy).ok_or(InvalidReportError::CannotDampen)?],
+            _ => policy
+                .directions()
+                .into_iter()
+                .filter_map(|increasing| dampen_levels(report, max_removals, increasing, policy))
+                .min_by_key(|removed| removed.len())
+                .ok_or(InvalidReportError::CannotDampen)?,
+        }
+    };
+    let dampened_report = report
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !removed.contains(index))
+        .map(|(_, &level)| level)
+        .collect();
+    return Ok(Dampening {
+        report: dampened_report,
+        removed,
+    });
+}
+
+fn dampen_report(
+    report: &Vec<isize>,
+    policy: &SafetyPolicy,
+) -> Result<Vec<isize>, InvalidReportError> {
+    return dampen_report_with_tolerance(report, 1, policy).map(|dampening| dampening.report);
+}
+
+pub fn report_safety(report: &Vec<isize>, dampener: bool, policy: &SafetyPolicy) -> bool {
+    if dampener {
+        return dampen_report(report, policy).is_ok();
+    }
+    return report_safety_no_dampener(report, policy);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnsafeReason {
+    DirectionFlip,
+    ZeroStep,
+    StepTooSmall,
+    StepTooLarge,
+}
+
+impl Display for UnsafeReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            UnsafeReason::DirectionFlip => "direction flip",
+            UnsafeReason::ZeroStep => "zero step",
+            UnsafeReason::StepTooSmall => "step too small",
+            UnsafeReason::StepTooLarge => "step too large",
+        };
+        write!(f, "{reason}")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ReportDiagnostic {
+    // Required by the policy, or else given by the first non-zero step
+    pub trend: Option<Trend>,
+    // Index of the first level that makes the report unsafe, and why
+    pub failure: Option<(usize, UnsafeReason)>,
+    pub dampened: Option<Dampening>,
+}
+
+impl ReportDiagnostic {
+    pub fn is_safe(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+impl Display for ReportDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.trend {
+            Some(Trend::Increasing) => write!(f, "increasing")?,
+            Some(Trend::Decreasing) => write!(f, "decreasing")?,
+            None => write!(f, "flat")?,
+        }
+        if let Some((index, reason)) = self.failure {
+            write!(f, ", {reason} at index {index}")?;
+            match &self.dampened {
+                Some(dampening) => write!(f, ", safe removing index {:?}", dampening.removed)?,
+                None => write!(f, ", cannot dampen")?,
+            }
+        } else {
+            write!(f, ", safe")?;
+        }
+        Ok(())
+    }
+}
+
+fn unsafe_reason(step: isize, increasing: bool, policy: &SafetyPolicy) -> Option<UnsafeReason> {
+    let step = if increasing { step } else { -step };
+    if step == 0 {
+        return if policy.strict {
+            Some(UnsafeReason::ZeroStep)
+        } else {
+            None
+        };
+    }
+    if step < 0 {
+        return Some(UnsafeReason::DirectionFlip);
+    }
+    if step < policy.min_step {
+        return Some(UnsafeReason::StepTooSmall);
+    }
+    if step > policy.max_step {
+        return Some(UnsafeReason::StepTooLarge);
+    }
+    return None;
+}
+
+pub fn diagnose_report(report: &Vec<isize>, policy: &SafetyPolicy) -> ReportDiagnostic {
+    let steps: Vec<isize> = report_diffs(report).iter().map(|&d| -d).collect();
+    let trend = policy.direction.or_else(|| {
+        steps.iter().find(|&&step| step != 0).map(|&step| {
+            if step > 0 {
+                Trend::Increasing
+            } else {
+                Trend::Decreasing
+            }
+        })
+    });
+    let increasing = trend != Some(Trend::Decreasing);
+    let failure = steps.iter().enumerate().find_map(|(index, &step)| {
+        unsafe_reason(step, increasing, policy).map(|reason| (index + 1, reason))
+    });
+    return ReportDiagnostic {
+        trend,
+        failure,
+        dampened: dampen_report_with_tolerance(report, 1, policy).ok(),
+    };
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ReportSummary {
+    pub safe: usize,
+    pub safe_after_dampening: usize,
+    pub unsafe_reports: usize,
+    // First failure of every report that is not safe on its own
+    pub failure_reasons: BTreeMap<UnsafeReason, usize>,
+    // Signed steps between consecutive levels, across all reports
+    pub step_sizes: BTreeMap<isize, usize>,
+    pub min_length: usize,
+    pub max_length: usize,
+    pub mean_length: f64,
+}
+
+pub fn summarize_reports(reports: &Vec<Vec<isize>>, policy: &SafetyPolicy) -> ReportSummary {
+    let mut summary = ReportSummary {
+        safe: 0,
+        safe_after_dampening: 0,
+        unsafe_reports: 0,
+        failure_reasons: BTreeMap::new(),
+        step_sizes: BTreeMap::new(),
+        min_length: reports.iter().map(|r| r.len()).min().unwrap_or(0),
+        max_length: reports.iter().map(|r| r.len()).max().unwrap_or(0),
+        mean_length: 0.0,
+    };
+    if !reports.is_empty() {
+        let total_length: usize = reports.iter().map(|r| r.len()).sum();
+        summary.mean_length = total_length as f64 / reports.len() as f64;
+    }
+
+    for report in reports {
+        let diagnostic = diagnose_report(report, policy);
+        if let Some((_, reason)) = diagnostic.failure {
+            *summary.failure_reasons.entry(reason).or_default() += 1;
+            if diagnostic.dampened.is_some() {
+                summary.safe_after_dampening += 1;
+            } else {
+                summary.unsafe_reports += 1;
+            }
+        } else {
+            summary.safe += 1;
+        }
+        for diff in report_diffs(report) {
+            *summary.step_sizes.entry(-diff).or_default() += 1;
+        }
+    }
+    return summary;
+}
+
+pub fn count_safe_reports(
+    reports: &Vec<Vec<isize>>,
+    dampener: bool,
+    policy: &SafetyPolicy,
+) -> usize {
+    return reports
+        .iter()
+        .map(|r| report_safety(r, dampener, policy))
+        .filter(|&s| s)
+        .count();
+}
+
+pub fn parse(contents: String) -> Vec<Vec<isize>> {
+    let lines = contents.lines();
+    let mut reports: Vec<Vec<isize>> = Vec::new();
+    for line in lines {
+        let mut report: Vec<isize> = Vec::new();
+        for num_str in line.split_whitespace() {
+            report.push(num_str.parse::<isize>().unwrap());
+        }
+        reports.push(report);
+    }
+
+    return reports;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_works() {
+        let reports = parse(String::from("7 6 4 2 1\n1 2 7 8 9\n"));
+        assert_eq!(reports, vec![vec![7, 6, 4, 2, 1], vec![1, 2, 7, 8, 9]]);
+    }
+
+    #[test]
+    fn report_safety_no_dampener_works() {
+        let safe_report1 = vec![7, 6, 4, 2, 1];
+        let safe_report2 = vec![1, 3, 6, 7, 9];
+        let unsafe_report1 = vec![1, 2, 7, 8, 9];
+        let unsafe_report2 = vec![9, 7, 6, 2, 1];
+        let unsafe_report3 = vec![1, 3, 2, 4, 5];
+        let unsafe_report4 = vec![8, 6, 4, 4, 1];
+
+        assert!(report_safety(
+            &safe_report1,
+            false,
+            &SafetyPolicy::default()
+        ));
+        assert!(report_safety(
+            &safe_report2,
+            false,
+            &SafetyPolicy::default()
+        ));
+
+        assert!(!report_safety(
+            &unsafe_report1,
+            false,
+            &SafetyPolicy::default()
+        ));
+        assert!(!report_safety(
+            &unsafe_report2,
+            false,
+            &SafetyPolicy::default()
+        ));
+        assert!(!report_safety(
+            &unsafe_report3,
+            false,
+            &SafetyPolicy::default()
+        ));
+        assert!(!report_safety(
+            &unsafe_report4,
+            false,
+            &SafetyPolicy::default()
+        ));
+    }
+
+    #[test]
+    fn dampen_report_works() {
+        let safe_report1 = vec![7, 6, 4, 2, 1];
+        let safe_report2 = vec![1, 3, 6, 7, 9];
+        let safe_report_with_dampening1 = vec![1, 3, 2, 4, 5];
+        let safe_report_with_dampening2 = vec![8, 6, 4, 4, 1];
+
+        assert_eq!(
+            dampen_report(&safe_report1, &SafetyPolicy::default()).unwrap(),
+            safe_report1
+        );
+        assert_eq!(
+            dampen_report(&safe_report2, &SafetyPolicy::default()).unwrap(),
+            safe_report2
+        );
+
+        assert_eq!(
+            dampen_report(&safe_report_with_dampening1, &SafetyPolicy::default()).unwrap(),
+            vec![1, 2, 4, 5]
+        );
+        assert_eq!(
+            dampen_report(&safe_report_with_dampening2, &SafetyPolicy::default()).unwrap(),
+            vec![8, 6, 4, 1]
+        );
+    }
+
+    #[test]
+    fn report_safety_dampener_works() {
+        let safe_report1 = vec![7, 6, 4, 2, 1];
+        let safe_report2 = vec![1, 3, 6, 7, 9];
+        let safe_report3 = vec![1, 3, 2, 4, 5];
+        let safe_report4 = vec![8, 6, 4, 4, 1];
+        let unsafe_report1 = vec![1, 2, 7, 8, 9];
+        let unsafe_report2 = vec![9, 7, 6, 2, 1];
+
+        assert!(report_safety(&safe_report1, true, &SafetyPolicy::default()));
+        assert!(report_safety(&safe_report2, true, &SafetyPolicy::default()));
+        assert!(report_safety(&safe_report3, true, &SafetyPolicy::default()));
+        assert!(report_safety(&safe_report4, true, &SafetyPolicy::default()));
+
+        assert!(!report_safety(
+            &unsafe_report1,
+            true,
+            &SafetyPolicy::default()
+        ));
+        assert!(!report_safety(
+            &unsafe_report2,
+            true,
+            &SafetyPolicy::default()
+        ));
+    }
+
+    #[test]
+    fn safety_policy_works() {
+        let report = vec![1, 3, 3, 7, 9];
+        let default_policy = SafetyPolicy::default();
+        let wide_policy = SafetyPolicy {
+            max_step: 4,
+            strict: false,
+            ..SafetyPolicy::default()
+        };
+        let decreasing_policy = SafetyPolicy {
+            direction: Some(Trend::Decreasing),
+            ..wide_policy
+        };
+        let big_steps_policy = SafetyPolicy {
+            min_step: 2,
+            max_step: 4,
+            ..SafetyPolicy::default()
+        };
+
+        assert!(!report_safety(&report, false, &default_policy));
+        assert!(report_safety(&report, false, &wide_policy));
+        assert!(!report_safety(&report, false, &decreasing_policy));
+        assert!(report_safety(
+            &vec![9, 7, 3, 3, 1],
+            false,
+            &decreasing_policy
+        ));
+        assert!(!report_safety(&report, false, &big_steps_policy));
+        assert!(report_safety(&report, true, &big_steps_policy));
+
+        let reports = vec![report.clone(), vec![7, 6, 4, 2, 1], vec![1, 2, 3]];
+        assert_eq!(count_safe_reports(&reports, false, &default_policy), 2);
+        assert_eq!(count_safe_reports(&reports, false, &wide_policy), 3);
+        assert_eq!(count_safe_reports(&reports, false, &decreasing_policy), 1);
+    }
+
+    #[test]
+    fn diagnose_report_works() {
+        let policy = SafetyPolicy::default();
+
+        let diagnostic = diagnose_report(&vec![7, 6, 4, 2, 1], &policy);
+        assert_eq!(diagnostic.trend, Some(Trend::Decreasing));
+        assert!(diagnostic.is_safe());
+        assert_eq!(diagnostic.to_string(), "decreasing, safe");
+
+        let diagnostic = diagnose_report(&vec![1, 2, 7, 8, 9], &policy);
+        assert_eq!(diagnostic.failure, Some((2, UnsafeReason::StepTooLarge)));
+        assert_eq!(diagnostic.dampened, None);
+        assert_eq!(
+            diagnostic.to_string(),
+            "increasing, step too large at index 2, cannot dampen"
+        );
+
+        let diagnostic = diagnose_report(&vec![1, 3, 2, 4, 5], &policy);
+        assert_eq!(diagnostic.trend, Some(Trend::Increasing));
+        assert_eq!(diagnostic.failure, Some((2, UnsafeReason::DirectionFlip)));
+        assert_eq!(
+            diagnostic.dampened.as_ref().unwrap().report,
+            vec![1, 2, 4, 5]
+        );
+        assert_eq!(
+            diagnostic.to_string(),
+            "increasing, direction flip at index 2, safe removing index [1]"
+        );
+
+        let diagnostic = diagnose_report(&vec![8, 6, 4, 4, 1], &policy);
+        assert_eq!(diagnostic.failure, Some((3, UnsafeReason::ZeroStep)));
+
+        let big_steps_policy = SafetyPolicy {
+            min_step: 2,
+            ..policy
+        };
+        let diagnostic = diagnose_report(&vec![1, 3, 4], &big_steps_policy);
+        assert_eq!(diagnostic.failure, Some((2, UnsafeReason::StepTooSmall)));
+    }
+
+    #[test]
+    fn summarize_reports_works() {
+        let reports = vec![
+            vec![7, 6, 4, 2, 1],
+            vec![1, 2, 7, 8, 9],
+            vec![9, 7, 6, 2, 1],
+            vec![1, 3, 2, 4, 5],
+            vec![8, 6, 4, 4, 1],
+            vec![1, 3, 6, 7, 9, 10],
+        ];
+        let policy = SafetyPolicy::default();
+        let summary = summarize_reports(&reports, &policy);
+
+        assert_eq!(summary.safe, count_safe_reports(&reports, false, &policy));
+        assert_eq!(
+            summary.safe + summary.safe_after_dampening,
+            count_safe_reports(&reports, true, &policy)
+        );
+        assert_eq!(
+            (
+                summary.safe,
+                summary.safe_after_dampening,
+                summary.unsafe_reports
+            ),
+            (2, 2, 2)
+        );
+        assert_eq!(
+            summary.failure_reasons,
+            BTreeMap::from([
+                (UnsafeReason::DirectionFlip, 1),
+                (UnsafeReason::ZeroStep, 1),
+                (UnsafeReason::StepTooLarge, 2),
+            ])
+        );
+        assert_eq!(summary.step_sizes.get(&-2), Some(&5));
+        assert_eq!(summary.step_sizes.get(&0), Some(&1));
+        assert_eq!(summary.step_sizes.values().sum::<usize>(), 25);
+        assert_eq!((summary.min_length, summary.max_length), (5, 6));
+        assert!((summary.mean_length - 31.0 / 6.0).abs() < 1e-9);
+    }
+
+    fn dampen_report_brute_force(report: &Vec<isize>, max_removals: usize) -> Option<usize> {
+        // Fewest removals that make the report safe, trying every subset of levels
+        return (0..(1usize << report.len()))
+            .filter(|mask| mask.count_ones() as usize <= max_removals)
+            .filter(|mask| {
+                let kept: Vec<isize> = (0..report.len())
+                    .filter(|index| mask & (1 << index) == 0)
+                    .map(|index| report[index])
+                    .collect();
+                !kept.is_empty() && report_safety_no_dampener(&kept, &SafetyPolicy::default())
+            })
+            .map(|mask| mask.count_ones() as usize)
+            .min();
+    }
+
+    #[test]
+    fn dampen_report_with_tolerance_works() {
+        let report = vec![1, 3, 2, 4, 5];
+        assert_eq!(
+            dampen_report_with_tolerance(&report, 1, &SafetyPolicy::default()).unwrap(),
+            Dampening {
+                report: vec![1, 2, 4, 5],
+                removed: vec![1],
+            }
+        );
+
+        let report = vec![1, 9, 2, 8, 3, 4];
+        assert!(dampen_report_with_tolerance(&report, 1, &SafetyPolicy::default()).is_err());
+        assert_eq!(
+            dampen_report_with_tolerance(&report, 2, &SafetyPolicy::default()).unwrap(),
+            Dampening {
+                report: vec![1, 2, 3, 4],
+                removed: vec![1, 3],
+            }
+        );
+
+        let safe_report = vec![7, 6, 4, 2, 1];
+        assert_eq!(
+            dampen_report_with_tolerance(&safe_report, 0, &SafetyPolicy::default())
+                .unwrap()
+                .removed,
+            vec![]
+        );
+    }
+
+    #[test]
+    fn dampen_report_with_tolerance_matches_brute_force() {
+        // Every report of up to 5 levels drawn from a small range
+        let levels = [1, 2, 3, 5, 8];
+        let mut reports: Vec<Vec<isize>> = Vec::new();
+        for length in 1..=5 {
+            for combination in 0..levels.len().pow(length) {
+                let report: Vec<isize> = (0..length)
+                    .map(|position| levels[combination / levels.len().pow(position) % levels.len()])
+                    .collect();
+                reports.push(report);
+            }
+        }
+
+        for report in reports.iter() {
+            for max_removals in 0..=3 {
+                let dampening =
+                    dampen_report_with_tolerance(report, max_removals, &SafetyPolicy::default());
+                let expected = dampen_report_brute_force(report, max_removals);
+                assert_eq!(
+                    dampening.as_ref().ok().map(|d| d.removed.len()),
+                    expected,
+                    "{report:?} with {max_removals} removals"
+                );
+                if let Ok(dampening) = dampening {
+                    assert!(report_safety_no_dampener(
+                        &dampening.report,
+                        &SafetyPolicy::default()
+                    ));
+                }
+            }
+        }
+    }
+}
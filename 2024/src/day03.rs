@@ -0,0 +1,1181 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter, Write as _};
+use std::io::{self, BufRead};
+
+use num_bigint::BigInt;
+use regex::{bytes, Regex};
+
+use crate::{Accumulation, ArithmeticError};
+
+// Operations work on big integers, so that their results are always exact
+pub type Evaluator = fn(&[BigInt]) -> BigInt;
+
+// Digits of `isize::MAX`, so wider operands can never fit
+pub const MAX_OPERAND_DIGITS: usize = isize::MAX.ilog10() as usize + 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Op(String, Vec<isize>),
+    Do,
+    Dont,
+    If(isize),
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    // A single on/off toggle, as in the puzzle
+    Flat,
+    // don't() nests, and if(x)/end() blocks scope it
+    Nested,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ProgramError {
+    UnbalancedEnd { offset: usize },
+    UnclosedIf { offset: usize },
+    Arithmetic(ArithmeticError),
+}
+
+impl From<ArithmeticError> for ProgramError {
+    fn from(error: ArithmeticError) -> ProgramError {
+        ProgramError::Arithmetic(error)
+    }
+}
+
+#[derive(Debug)]
+pub enum StreamError {
+    Io(io::Error),
+    Program(ProgramError),
+}
+
+impl From<io::Error> for StreamError {
+    fn from(error: io::Error) -> StreamError {
+        StreamError::Io(error)
+    }
+}
+
+impl From<ProgramError> for StreamError {
+    fn from(error: ProgramError) -> StreamError {
+        StreamError::Program(error)
+    }
+}
+
+impl From<ArithmeticError> for StreamError {
+    fn from(error: ArithmeticError) -> StreamError {
+        StreamError::Program(ProgramError::Arithmetic(error))
+    }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Op(name, operands) => {
+                let operands: Vec<String> = operands.iter().map(|o| o.to_string()).collect();
+                write!(f, "{}({})", name, operands.join(","))
+            }
+            Token::Do => write!(f, "do()"),
+            Token::Dont => write!(f, "don't()"),
+            Token::If(condition) => write!(f, "if({condition})"),
+            Token::End => write!(f, "end()"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub token: Token,
+    // Byte offset of the instruction in the program
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Execution {
+    pub instruction: Instruction,
+    pub enabled: bool,
+    // Result of the operation, if the instruction is one
+    pub value: Option<BigInt>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InstructionSet {
+    // Name -> arity -> evaluator
+    operations: HashMap<String, HashMap<usize, Evaluator>>,
+    min_digits: usize,
+    max_digits: usize,
+    dialect: Dialect,
+}
+
+impl InstructionSet {
+    pub fn new() -> InstructionSet {
+        InstructionSet {
+            operations: HashMap::new(),
+            min_digits: 1,
+            max_digits: 3,
+            dialect: Dialect::Flat,
+        }
+    }
+
+    pub fn with_operand_width(mut self, min_digits: usize, max_digits: usize) -> InstructionSet {
+        assert!(
+            (1..=max_digits).contains(&min_digits) && max_digits <= MAX_OPERAND_DIGITS,
+            "Operand width should satisfy 1 <= {min_digits} <= {max_digits} <= {MAX_OPERAND_DIGITS}"
+        );
+        self.min_digits = min_digits;
+        self.max_digits = max_digits;
+        self
+    }
+
+    pub fn with_dialect(mut self, dialect: Dialect) -> InstructionSet {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    pub fn register(mut self, name: &str, arity: usize, evaluator: Evaluator) -> InstructionSet {
+        assert!(!name.is_empty(), "Operation name should not be empty");
+        self.operations
+            .entry(name.to_string())
+            .or_default()
+            .insert(arity, evaluator);
+        self
+    }
+
+    fn evaluator(&self, name: &str, arity: usize) -> Option<&Evaluator> {
+        return self
+            .operations
+            .get(name)
+            .and_then(|arities| arities.get(&arity));
+    }
+
+    pub fn evaluate(&self, name: &str, operands: &[isize]) -> Option<BigInt> {
+        let operands: Vec<BigInt> = operands.iter().map(|&o| BigInt::from(o)).collect();
+        return self
+            .evaluator(name, operands.len())
+            .map(|evaluator| evaluator(&operands));
+    }
+
+    fn max_arity(&self) -> usize {
+        return self
+            .operations
+            .values()
+            .flat_map(|arities| arities.keys())
+            .copied()
+            .max()
+            .unwrap_or(0);
+    }
+
+    fn max_instruction_len(&self) -> usize {
+        // Every instruction fits in this many bytes, which bounds what streaming
+        // has to keep around between chunks
+        let max_name_len = self.operations.keys().map(|n| n.len()).max().unwrap_or(0);
+        let max_arity = self.max_arity();
+        let max_op_len = max_name_len + 2 + max_arity * (self.max_digits + 1);
+        let max_if_len = "if()".len() + self.max_digits;
+        return max_op_len.max(max_if_len).max("don't()".len());
+    }
+
+    fn pattern(&self) -> String {
+        let operand = format!("[0-9]{{{},{}}}", self.min_digits, self.max_digits);
+        let conditionals = match self.dialect {
+            Dialect::Flat => String::from(r"do\(\)|don't\(\)"),
+            Dialect::Nested => format!(r"do\(\)|don't\(\)|if\({operand}\)|end\(\)"),
+        };
+        if self.operations.is_empty() {
+            return conditionals;
+        }
+        // Longer names first, so that they are not shadowed by their prefixes
+        let mut names: Vec<&String> = self.operations.keys().collect();
+        names.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        let names = names
+            .iter()
+            .map(|name| regex::escape(name))
+            .collect::<Vec<String>>()
+            .join("|");
+        // Operands are optional, for operations without any
+        let max_extra_operands = self.max_arity().saturating_sub(1);
+        return format!(
+            r"(?:({names})\(((?:{operand}(?:,{operand}){{0,{max_extra_operands}}})?)\))|{conditionals}"
+        );
+    }
+
+    fn token(&self, matched: &str, name: &str, operands: &str) -> Option<Token> {
+        if name.is_empty() {
+            return match matched {
+                "do()" => Some(Token::Do),
+                "don't()" => Some(Token::Dont),
+                "end()" => Some(Token::End),
+                // Conditions that do not fit are just more corruption
+                _ => matched["if(".len()..matched.len() - 1]
+                    .parse::<isize>()
+                    .ok()
+                    .map(Token::If),
+            };
+        }
+        // Operands that do not fit are just more corruption
+        let operands: Vec<isize> = operands
+            .split_terminator(",")
+            .map(|o| o.parse::<isize>().ok())
+            .collect::<Option<Vec<isize>>>()?;
+        // Known names with an unknown arity are too
+        self.evaluator(name, operands.len())?;
+        return Some(Token::Op(name.to_string(), operands));
+    }
+}
+
+impl Default for InstructionSet {
+    // The instruction set of the puzzle
+    fn default() -> InstructionSet {
+        InstructionSet::new().register("mul", 2, |ops| &ops[0] * &ops[1])
+    }
+}
+
+pub fn extended_instruction_set() -> InstructionSet {
+    return InstructionSet::default()
+        .register("mul", 3, |ops| &ops[0] * &ops[1] * &ops[2])
+        .register("add", 2, |ops| &ops[0] + &ops[1])
+        .register("sub", 2, |ops| &ops[0] - &ops[1]);
+}
+
+pub fn tokenize(program: &str, instruction_set: &InstructionSet) -> Vec<Instruction> {
+    let re = Regex::new(&instruction_set.pattern()).unwrap();
+    let mut instructions: Vec<Instruction> = Vec::new();
+    for captures in re.captures_iter(program) {
+        let matched = captures.get(0).unwrap();
+        let name = captures.get(1).map_or("", |m| m.as_str());
+        let operands = captures.get(2).map_or("", |m| m.as_str());
+        if let Some(token) = instruction_set.token(matched.as_str(), name, operands) {
+            instructions.push(Instruction {
+                token,
+                offset: matched.start(),
+            });
+        }
+    }
+    return instructions;
+}
+
+fn match_str(m: Option<bytes::Match<'_>>) -> &str {
+    // The pattern only matches ASCII
+    return m.map_or("", |m| std::str::from_utf8(m.as_bytes()).unwrap());
+}
+
+pub struct InstructionStream<'a, R: BufRead> {
+    reader: R,
+    instruction_set: &'a InstructionSet,
+    re: bytes::Regex,
+    max_instruction_len: usize,
+    // Unprocessed bytes, starting at `buffer_offset` in the program
+    buffer: Vec<u8>,
+    buffer_offset: usize,
+    pending: VecDeque<Instruction>,
+    finished: bool,
+}
+
+impl<'a, R: BufRead> InstructionStream<'a, R> {
+    pub fn new(reader: R, instruction_set: &'a InstructionSet) -> InstructionStream<'a, R> {
+        InstructionStream {
+            reader,
+            instruction_set,
+            re: bytes::Regex::new(&instruction_set.pattern()).unwrap(),
+            max_instruction_len: instruction_set.max_instruction_len(),
+            buffer: Vec::new(),
+            buffer_offset: 0,
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    fn read_chunk(&mut self) -> io::Result<()> {
+        let chunk = self.reader.fill_buf()?;
+        if chunk.is_empty() {
+            self.finished = true;
+        }
+        let chunk_len = chunk.len();
+        self.buffer.extend_from_slice(chunk);
+        self.reader.consume(chunk_len);
+        return Ok(());
+    }
+
+    fn scan_buffer(&mut self) {
+        // Matches starting this close to the end might still grow with the next chunk,
+        // or hide an earlier match that does, so they wait unless the input is over
+        let safe_len = if self.finished {
+            self.buffer.len()
+        } else {
+            self.buffer
+                .len()
+                .saturating_sub(self.max_instruction_len - 1)
+        };
+        let mut processed_len = safe_len;
+        for captures in self.re.captures_iter(&self.buffer) {
+            let matched = captures.get(0).unwrap();
+            if matched.start() >= safe_len {
+                break;
+            }
+            let name = match_str(captures.get(1));
+            let operands = match_str(captures.get(2));
+            if let Some(token) =
+                self.instruction_set
+                    .token(match_str(Some(matched)), name, operands)
+            {
+                self.pending.push_back(Instruction {
+                    token,
+                    offset: self.buffer_offset + matched.start(),
+                });
+            }
+            processed_len = processed_len.max(matched.end());
+        }
+        self.buffer.drain(..processed_len);
+        self.buffer_offset += processed_len;
+    }
+}
+
+impl<R: BufRead> Iterator for InstructionStream<'_, R> {
+    type Item = io::Result<Instruction>;
+
+    fn next(&mut self) -> Option<io::Result<Instruction>> {
+        loop {
+            if let Some(instruction) = self.pending.pop_front() {
+                return Some(Ok(instruction));
+            }
+            if self.finished {
+                return None;
+            }
+            if let Err(error) = self.read_chunk() {
+                self.finished = true;
+                return Some(Err(error));
+            }
+            self.scan_buffer();
+        }
+    }
+}
+
+pub struct Interpreter<'a> {
+    instruction_set: &'a InstructionSet,
+    conditionals: bool,
+    // Number of pending don't(), which is at most 1 in the flat dialect
+    disabled_depth: usize,
+    // Open if() blocks: their condition, the depth to restore at end() and their offset
+    blocks: Vec<(bool, usize, usize)>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(instruction_set: &'a InstructionSet, conditionals: bool) -> Interpreter<'a> {
+        Interpreter {
+            instruction_set,
+            conditionals,
+            disabled_depth: 0,
+            blocks: Vec::new(),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        return (self.disabled_depth == 0) & self.blocks.iter().all(|&(condition, _, _)| condition);
+    }
+
+    pub fn step(&mut self, instruction: Instruction) -> Result<Execution, ProgramError> {
+        // Without conditionals, do() and don't() are just ignored
+        let nested = self.instruction_set.dialect() == Dialect::Nested;
+        let mut value = None;
+        match &instruction.token {
+            Token::Do if self.conditionals => {
+                self.disabled_depth = self.disabled_depth.saturating_sub(1);
+            }
+            Token::Dont if self.conditionals & nested => self.disabled_depth += 1,
+            Token::Dont if self.conditionals => self.disabled_depth = 1,
+            Token::If(condition) if self.conditionals => {
+                self.blocks
+                    .push((*condition != 0, self.disabled_depth, instruction.offset));
+            }
+            Token::End if self.conditionals => {
+                let Some((_, disabled_depth, _)) = self.blocks.pop() else {
+                    return Err(ProgramError::UnbalancedEnd {
+                        offset: instruction.offset,
+                    });
+                };
+                // A don't() inside the block does not leak out of it
+                self.disabled_depth = disabled_depth;
+            }
+            Token::Op(name, operands) => value = self.instruction_set.evaluate(name, operands),
+            _ => (),
+        }
+        return Ok(Execution {
+            instruction,
+            enabled: self.enabled(),
+            value,
+        });
+    }
+
+    pub fn finish(&self) -> Result<(), ProgramError> {
+        if let Some(&(_, _, offset)) = self.blocks.first() {
+            return Err(ProgramError::UnclosedIf { offset });
+        }
+        return Ok(());
+    }
+}
+
+pub fn interpret(
+    instructions: &[Instruction],
+    conditionals: bool,
+    instruction_set: &InstructionSet,
+) -> Result<Vec<Execution>, ProgramError> {
+    let mut interpreter = Interpreter::new(instruction_set, conditionals);
+    let executions = instructions
+        .iter()
+        .map(|instruction| interpreter.step(instruction.clone()))
+        .collect::<Result<Vec<Execution>, ProgramError>>()?;
+    interpreter.finish()?;
+    return Ok(executions);
+}
+
+fn to_native(value: &BigInt) -> Result<isize, ArithmeticError> {
+    return isize::try_from(value).map_err(|_| ArithmeticError::Overflow);
+}
+
+pub fn total(executions: &[Execution]) -> Result<isize, ArithmeticError> {
+    return executions
+        .iter()
+        .filter(|e| e.enabled)
+        .filter_map(|e| e.value.as_ref())
+        .try_fold(0isize, |acc, v| {
+            acc.checked_add(to_native(v)?)
+                .ok_or(ArithmeticError::Overflow)
+        });
+}
+
+pub fn total_with(
+    executions: &[Execution],
+    accumulation: Accumulation,
+) -> Result<BigInt, ArithmeticError> {
+    return match accumulation {
+        Accumulation::Checked => total(executions).map(BigInt::from),
+        Accumulation::ArbitraryPrecision => Ok(executions
+            .iter()
+            .filter(|e| e.enabled)
+            .filter_map(|e| e.value.as_ref())
+            .sum()),
+    };
+}
+
+pub fn run_program_with(
+    program: &str,
+    conditionals: bool,
+    instruction_set: &InstructionSet,
+) -> Result<isize, ProgramError> {
+    let instructions = tokenize(program, instruction_set);
+    let executions = interpret(&instructions, conditionals, instruction_set)?;
+    return Ok(total(&executions)?);
+}
+
+pub fn run_program(program: &str, conditionals: bool) -> isize {
+    // The flat dialect has no blocks, so it cannot be unbalanced,
+    // and products of three-digit operands are far from overflowing
+    return run_program_with(program, conditionals, &InstructionSet::default()).unwrap();
+}
+
+pub fn run_program_streaming<R: BufRead>(
+    reader: R,
+    conditionals: bool,
+    instruction_set: &InstructionSet,
+) -> Result<isize, StreamError> {
+    let mut interpreter = Interpreter::new(instruction_set, conditionals);
+    let mut total: isize = 0;
+    for instruction in InstructionStream::new(reader, instruction_set) {
+        let execution = interpreter.step(instruction?)?;
+        if execution.enabled {
+            total = total
+                .checked_add(execution.value.as_ref().map_or(Ok(0), to_native)?)
+                .ok_or(ArithmeticError::Overflow)?;
+        }
+    }
+    interpreter.finish()?;
+    return Ok(total);
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TraceEntry {
+    pub offset: usize,
+    pub instruction: String,
+    pub enabled: bool,
+    pub value: Option<BigInt>,
+    // Running total after this instruction
+    pub total: isize,
+}
+
+pub fn trace(executions: &[Execution]) -> Result<Vec<TraceEntry>, ArithmeticError> {
+    let mut total: isize = 0;
+    let mut entries: Vec<TraceEntry> = Vec::new();
+    for execution in executions {
+        if execution.enabled {
+            total = total
+                .checked_add(execution.value.as_ref().map_or(Ok(0), to_native)?)
+                .ok_or(ArithmeticError::Overflow)?;
+        }
+        entries.push(TraceEntry {
+            offset: execution.instruction.offset,
+            instruction: execution.instruction.token.to_string(),
+            enabled: execution.enabled,
+            value: execution.value.clone(),
+            total,
+        });
+    }
+    return Ok(entries);
+}
+
+pub fn format_trace_table(entries: &[TraceEntry]) -> String {
+    let mut table = format!(
+        "{:>8}  {:<16}  {:<8}  {:>12}  {:>12}\n",
+        "offset", "instruction", "state", "value", "total"
+    );
+    for entry in entries {
+        let state = if entry.enabled { "enabled" } else { "disabled" };
+        let value = entry
+            .value
+            .as_ref()
+            .map_or(String::from("-"), |v| v.to_string());
+        writeln!(
+            table,
+            "{:>8}  {:<16}  {:<8}  {:>12}  {:>12}",
+            entry.offset, entry.instruction, state, value, entry.total
+        )
+        .unwrap();
+    }
+    return table;
+}
+
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str(r#"\""#),
+            '\\' => escaped.push_str(r"\\"),
+            c if c.is_control() => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+pub fn format_trace_json_lines(entries: &[TraceEntry]) -> String {
+    // Registered names can be any string, so they need escaping
+    let mut lines = String::new();
+    for entry in entries {
+        let value = entry
+            .value
+            .as_ref()
+            .map_or(String::from("null"), |v| v.to_string());
+        writeln!(
+            lines,
+            r#"{{"offset":{},"instruction":"{}","enabled":{},"value":{},"total":{}}}"#,
+            entry.offset,
+            escape_json(&entry.instruction),
+            entry.enabled,
+            value,
+            entry.total
+        )
+        .unwrap();
+    }
+    return lines;
+}
+
+fn cleanup_program(program: String) -> Vec<(isize, isize)> {
+    let re = Regex::new(r"mul\((\d{1,3}),(\d{1,3})\)").unwrap();
+    let mut mul_ops: Vec<(isize, isize)> = Vec::new();
+    for (_, [left, right]) in re.captures_iter(&program).map(|c| c.extract()) {
+        mul_ops.push((
+            left.parse::<isize>().unwrap(),
+            right.parse::<isize>().unwrap(),
+        ));
+    }
+    return mul_ops;
+}
+
+fn execute_mul_ops(mul_ops: Vec<(isize, isize)>) -> isize {
+    return mul_ops.iter().fold(0, |acc, (l, r)| acc + l * r);
+}
+
+fn erase_disabled_code(program: String) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    let mut index = 0;
+    while index < program.len() {
+        if let Some(start_disable) = program[index..].find("don't()") {
+            parts.push(&program[index..index + start_disable + 7]);
+            index += start_disable + 7;
+            if let Some(end_disable) = program[index..].find("do()") {
+                index += end_disable;
+            } else {
+                break;
+            }
+        } else {
+            parts.push(&program[index..]);
+            break;
+        }
+    }
+    return parts.join("");
+}
+
+pub fn run_mul_program(program: String, conditionals: bool) -> isize {
+    let mut program = program;
+    if conditionals {
+        program = erase_disabled_code(program);
+    }
+    let mul_ops = cleanup_program(program);
+    return execute_mul_ops(mul_ops);
+}
+
+pub fn run_mul_program_with(
+    program: String,
+    conditionals: bool,
+    accumulation: Accumulation,
+) -> Result<BigInt, ArithmeticError> {
+    let instruction_set = InstructionSet::default();
+    let instructions = tokenize(&program, &instruction_set);
+    // The flat dialect has no blocks, so it cannot be unbalanced
+    let executions = interpret(&instructions, conditionals, &instruction_set).unwrap();
+    return total_with(&executions, accumulation);
+}
+
+pub fn parse(contents: String) -> Vec<Instruction> {
+    return tokenize(&contents, &InstructionSet::default());
+}
+
+pub fn run_instructions(
+    instructions: &[Instruction],
+    conditionals: bool,
+) -> Result<isize, ProgramError> {
+    let executions = interpret(instructions, conditionals, &InstructionSet::default())?;
+    return Ok(total(&executions)?);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tokenize_works() {
+        let corrupted_program =
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        let instructions = tokenize(corrupted_program, &InstructionSet::default());
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction {
+                    token: Token::Op("mul".to_string(), vec![2, 4]),
+                    offset: 1
+                },
+                Instruction {
+                    token: Token::Dont,
+                    offset: 20
+                },
+                Instruction {
+                    token: Token::Op("mul".to_string(), vec![5, 5]),
+                    offset: 28
+                },
+                Instruction {
+                    token: Token::Op("mul".to_string(), vec![11, 8]),
+                    offset: 48
+                },
+                Instruction {
+                    token: Token::Do,
+                    offset: 59
+                },
+                Instruction {
+                    token: Token::Op("mul".to_string(), vec![8, 5]),
+                    offset: 64
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn interpret_works() {
+        let corrupted_program =
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        let instructions = tokenize(corrupted_program, &InstructionSet::default());
+
+        let enabled: Vec<bool> = interpret(&instructions, true, &InstructionSet::default())
+            .unwrap()
+            .iter()
+            .map(|e| e.enabled)
+            .collect();
+        assert_eq!(enabled, [true, false, false, false, true, true]);
+
+        assert!(interpret(&instructions, false, &InstructionSet::default())
+            .unwrap()
+            .iter()
+            .all(|e| e.enabled));
+    }
+
+    #[test]
+    fn run_program_works() {
+        let corrupted_program =
+            "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
+        assert_eq!(run_program(corrupted_program, false), 161);
+
+        let corrupted_program_conditionals =
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        assert_eq!(run_program(corrupted_program_conditionals, false), 161);
+        assert_eq!(run_program(corrupted_program_conditionals, true), 48);
+        assert_eq!(
+            run_program(corrupted_program_conditionals, true),
+            run_mul_program(corrupted_program_conditionals.to_string(), true)
+        );
+    }
+
+    #[test]
+    fn extended_instruction_set_works() {
+        let program = "mul(2,3,4)add(1,2)?sub(10,4)mul(2,4)adds(1,1)sub(1,2,3)mul(1234,2)";
+        let instruction_set = extended_instruction_set();
+        let names: Vec<String> = tokenize(program, &instruction_set)
+            .iter()
+            .map(|i| match &i.token {
+                Token::Op(name, operands) => format!("{name}{operands:?}"),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            names,
+            ["mul[2, 3, 4]", "add[1, 2]", "sub[10, 4]", "mul[2, 4]"]
+        );
+        assert_eq!(
+            run_program_with(program, false, &instruction_set).unwrap(),
+            24 + 3 + 6 + 8
+        );
+
+        // Only mul with two operands is part of the puzzle
+        assert_eq!(run_program(program, false), 8);
+
+        let wide_instruction_set = InstructionSet::default().with_operand_width(1, 4);
+        assert_eq!(
+            run_program_with(program, false, &wide_instruction_set).unwrap(),
+            8 + 2468
+        );
+    }
+
+    #[test]
+    fn nullary_operations_work() {
+        let instruction_set = InstructionSet::default().register("nop", 0, |_| BigInt::from(7));
+        let program = "nop()mul()nop(1)mul(2,3)xnop()";
+        let tokens: Vec<Token> = tokenize(program, &instruction_set)
+            .into_iter()
+            .map(|i| i.token)
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::Op("nop".to_string(), vec![]),
+                Token::Op("mul".to_string(), vec![2, 3]),
+                Token::Op("nop".to_string(), vec![]),
+            ]
+        );
+        assert_eq!(tokens[0].to_string(), "nop()");
+        assert_eq!(run_program_with(program, false, &instruction_set), Ok(20));
+
+        for chunk_size in 1..=program.len() {
+            let reader = io::BufReader::with_capacity(chunk_size, program.as_bytes());
+            let result = run_program_streaming(reader, false, &instruction_set).unwrap();
+            assert_eq!(result, 20, "Chunk size {chunk_size}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Operation name")]
+    fn empty_operation_name_fails() {
+        let _ = InstructionSet::default().register("", 1, |ops| ops[0].clone());
+    }
+
+    #[test]
+    fn wide_operands_work() {
+        let instruction_set = InstructionSet::default()
+            .with_operand_width(1, MAX_OPERAND_DIGITS)
+            .with_dialect(Dialect::Nested);
+        let program = format!(
+            "mul(99999999999999999999,1)mul({},1)if(99999999999999999999)mul(2,2)",
+            isize::MAX
+        );
+        let tokens: Vec<Token> = tokenize(&program, &instruction_set)
+            .into_iter()
+            .map(|i| i.token)
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::Op("mul".to_string(), vec![isize::MAX, 1]),
+                Token::Op("mul".to_string(), vec![2, 2]),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Operand width")]
+    fn empty_operand_width_fails() {
+        let _ = InstructionSet::default().with_operand_width(0, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Operand width")]
+    fn reversed_operand_width_fails() {
+        let _ = InstructionSet::default().with_operand_width(3, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Operand width")]
+    fn overflowing_operand_width_fails() {
+        let _ = InstructionSet::default().with_operand_width(1, MAX_OPERAND_DIGITS + 1);
+    }
+
+    #[test]
+    fn total_with_overflow_works() {
+        let instruction_set = InstructionSet::new()
+            .with_operand_width(1, MAX_OPERAND_DIGITS)
+            .register("mul", 2, |ops| &ops[0] * &ops[1]);
+        let program = format!("mul({},1)mul({},1)", isize::MAX, isize::MAX);
+        let executions = interpret(
+            &tokenize(&program, &instruction_set),
+            false,
+            &instruction_set,
+        )
+        .unwrap();
+
+        assert_eq!(
+            total_with(&executions, Accumulation::Checked),
+            Err(ArithmeticError::Overflow)
+        );
+        assert_eq!(
+            total_with(&executions, Accumulation::ArbitraryPrecision),
+            Ok(BigInt::from(isize::MAX) * 2)
+        );
+    }
+
+    #[test]
+    fn product_overflow_works() {
+        let instruction_set = InstructionSet::default().with_operand_width(1, MAX_OPERAND_DIGITS);
+        let program = format!("mul({},2)mul(2,3)", isize::MAX);
+        let executions = interpret(
+            &tokenize(&program, &instruction_set),
+            false,
+            &instruction_set,
+        )
+        .unwrap();
+
+        assert_eq!(executions[0].value, Some(BigInt::from(isize::MAX) * 2));
+        assert_eq!(total(&executions), Err(ArithmeticError::Overflow));
+        assert_eq!(
+            total_with(&executions, Accumulation::Checked),
+            Err(ArithmeticError::Overflow)
+        );
+        // Products are exact, so only checked accumulation fails
+        assert_eq!(
+            total_with(&executions, Accumulation::ArbitraryPrecision),
+            Ok(BigInt::from(isize::MAX) * 2 + 6)
+        );
+        assert_eq!(trace(&executions), Err(ArithmeticError::Overflow));
+        assert_eq!(
+            run_program_with(&program, false, &instruction_set),
+            Err(ProgramError::Arithmetic(ArithmeticError::Overflow))
+        );
+
+        // Disabled operations do not count, even if they overflow
+        let disabled_program = format!("don't()mul({},2)do()mul(2,3)", isize::MAX);
+        assert_eq!(
+            run_program_with(&disabled_program, true, &instruction_set),
+            Ok(6)
+        );
+        let executions = interpret(
+            &tokenize(&disabled_program, &instruction_set),
+            true,
+            &instruction_set,
+        )
+        .unwrap();
+        assert_eq!(
+            trace(&executions).unwrap()[1].value,
+            Some(BigInt::from(isize::MAX) * 2)
+        );
+    }
+
+    #[test]
+    fn sum_overflow_works() {
+        let instruction_set = InstructionSet::default().with_operand_width(1, MAX_OPERAND_DIGITS);
+        let program = format!("mul({},1)mul(1,1)", isize::MAX);
+        let executions = interpret(
+            &tokenize(&program, &instruction_set),
+            false,
+            &instruction_set,
+        )
+        .unwrap();
+
+        assert_eq!(total(&executions), Err(ArithmeticError::Overflow));
+        assert_eq!(trace(&executions), Err(ArithmeticError::Overflow));
+
+        for program in [program.clone(), format!("mul({},2)", isize::MAX)] {
+            let reader = io::BufReader::with_capacity(4, program.as_bytes());
+            assert!(matches!(
+                run_program_streaming(reader, false, &instruction_set),
+                Err(StreamError::Program(ProgramError::Arithmetic(
+                    ArithmeticError::Overflow
+                )))
+            ));
+        }
+    }
+
+    #[test]
+    fn nested_dialect_works() {
+        let program = "don't()don't()do()mul(2,2)do()mul(3,3)";
+        let flat_instruction_set = InstructionSet::default();
+        let nested_instruction_set = InstructionSet::default().with_dialect(Dialect::Nested);
+
+        assert_eq!(
+            run_program_with(program, true, &flat_instruction_set),
+            Ok(13)
+        );
+        assert_eq!(
+            run_program_with(program, true, &nested_instruction_set),
+            Ok(9)
+        );
+        assert_eq!(
+            run_program_with(program, false, &nested_instruction_set),
+            Ok(13)
+        );
+    }
+
+    #[test]
+    fn nested_dialect_blocks_work() {
+        let program = "if(0)mul(2,2)end()mul(3,3)if(1)mul(1,1)don't()mul(5,5)end()mul(7,7)";
+        let nested_instruction_set = InstructionSet::default().with_dialect(Dialect::Nested);
+        assert_eq!(
+            run_program_with(program, true, &nested_instruction_set),
+            Ok(59)
+        );
+
+        // In the flat dialect blocks are just more corruption
+        assert_eq!(run_program(program, true), 4 + 9 + 1);
+
+        let nested_program = "if(1)mul(2,2)if(0)mul(3,3)end()mul(5,5)end()";
+        assert_eq!(
+            run_program_with(nested_program, true, &nested_instruction_set),
+            Ok(29)
+        );
+    }
+
+    #[test]
+    fn nested_dialect_unbalanced_fails() {
+        let nested_instruction_set = InstructionSet::default().with_dialect(Dialect::Nested);
+
+        assert_eq!(
+            run_program_with("mul(2,2)end()", true, &nested_instruction_set),
+            Err(ProgramError::UnbalancedEnd { offset: 8 })
+        );
+        assert_eq!(
+            run_program_with("if(1)mul(2,2)if(0)end()", true, &nested_instruction_set),
+            Err(ProgramError::UnclosedIf { offset: 0 })
+        );
+
+        let reader = io::BufReader::with_capacity(3, "if(1)mul(2,2)".as_bytes());
+        assert!(matches!(
+            run_program_streaming(reader, true, &nested_instruction_set),
+            Err(StreamError::Program(ProgramError::UnclosedIf { offset: 0 }))
+        ));
+        let reader = io::BufReader::with_capacity(3, "mul(2,2)end()".as_bytes());
+        assert!(matches!(
+            run_program_streaming(reader, true, &nested_instruction_set),
+            Err(StreamError::Program(ProgramError::UnbalancedEnd {
+                offset: 8
+            }))
+        ));
+    }
+
+    #[test]
+    fn instruction_stream_works() {
+        let program = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?\
+            mul(8,5))mul(2,3,4)add(1,2)?sub(10,4)adds(1,1)sub(1,2,3)mul(1234,2)mul(1,2,3,4)";
+        let program = format!("{program}if(1)mul(2,2)end()");
+        let program = program.as_str();
+        for instruction_set in [
+            InstructionSet::default(),
+            extended_instruction_set(),
+            extended_instruction_set().with_dialect(Dialect::Nested),
+        ] {
+            let expected = tokenize(program, &instruction_set);
+            // Every chunk size splits some instruction across chunks
+            for chunk_size in 1..=program.len() {
+                let reader = io::BufReader::with_capacity(chunk_size, program.as_bytes());
+                let instructions = InstructionStream::new(reader, &instruction_set)
+                    .collect::<io::Result<Vec<Instruction>>>()
+                    .unwrap();
+                assert_eq!(instructions, expected, "Chunk size {chunk_size}");
+            }
+        }
+    }
+
+    #[test]
+    fn run_program_streaming_works() {
+        let program = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        let reader = io::BufReader::with_capacity(3, program.as_bytes());
+        let result = run_program_streaming(reader, true, &InstructionSet::default()).unwrap();
+        assert_eq!(result, 48);
+
+        // Invalid UTF-8 is just more corruption
+        let bytes = [b"mul(2,4)\xff\xfemul(".as_slice(), b"3,3)"].concat();
+        let reader = io::BufReader::with_capacity(4, bytes.as_slice());
+        let result = run_program_streaming(reader, false, &InstructionSet::default()).unwrap();
+        assert_eq!(result, 17);
+    }
+
+    #[test]
+    fn trace_works() {
+        let program = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        let instruction_set = InstructionSet::default();
+        let executions =
+            interpret(&tokenize(program, &instruction_set), true, &instruction_set).unwrap();
+        let entries = trace(&executions).unwrap();
+
+        assert_eq!(entries.len(), 6);
+        assert_eq!(
+            entries[2],
+            TraceEntry {
+                offset: 28,
+                instruction: "mul(5,5)".to_string(),
+                enabled: false,
+                value: Some(BigInt::from(25)),
+                total: 8,
+            }
+        );
+        assert_eq!(entries.last().unwrap().total, 48);
+
+        let table = format_trace_table(&entries);
+        assert_eq!(
+            table.lines().nth(2).unwrap(),
+            "      20  don't()           disabled             -             8"
+        );
+
+        let json_lines = format_trace_json_lines(&entries);
+        assert_eq!(
+            json_lines.lines().next().unwrap(),
+            r#"{"offset":1,"instruction":"mul(2,4)","enabled":true,"value":8,"total":8}"#
+        );
+        assert_eq!(
+            json_lines.lines().nth(1).unwrap(),
+            r#"{"offset":20,"instruction":"don't()","enabled":false,"value":null,"total":8}"#
+        );
+    }
+
+    #[test]
+    fn format_trace_json_lines_escapes_names() {
+        let instruction_set = InstructionSet::new()
+            .register("q\"x", 1, |ops| ops[0].clone())
+            .register("b\\s\t", 1, |ops| ops[0].clone());
+        let executions = interpret(
+            &tokenize("q\"x(5)b\\s\t(6)", &instruction_set),
+            false,
+            &instruction_set,
+        )
+        .unwrap();
+        let json_lines = format_trace_json_lines(&trace(&executions).unwrap());
+        assert_eq!(
+            json_lines,
+            concat!(
+                r#"{"offset":0,"instruction":"q\"x(5)","enabled":true,"value":5,"total":5}"#,
+                "\n",
+                r#"{"offset":6,"instruction":"b\\s\u0009(6)","enabled":true,"value":6,"total":11}"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn run_instructions_works() {
+        let instructions = parse(String::from(
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))",
+        ));
+        assert_eq!(run_instructions(&instructions, false), Ok(161));
+        assert_eq!(run_instructions(&instructions, true), Ok(48));
+    }
+
+    #[test]
+    fn parse_works() {
+        let instructions = parse(String::from(
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))",
+        ));
+        assert_eq!(instructions.len(), 6);
+        assert_eq!(instructions[0].offset, 1);
+    }
+
+    #[test]
+    fn cleanup_program_works() {
+        let corrupted_program =
+            String::from("xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))");
+        let mul_ops = cleanup_program(corrupted_program);
+        assert_eq!(mul_ops, vec![(2, 4), (5, 5), (11, 8), (8, 5)]);
+    }
+
+    #[test]
+    fn execute_mul_ops_works() {
+        let mul_ops = vec![(2, 4), (5, 5), (11, 8), (8, 5)];
+        let result = execute_mul_ops(mul_ops);
+        assert_eq!(result, 161);
+    }
+
+    #[test]
+    fn run_mul_program_with_works() {
+        let corrupted_program = String::from(
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))",
+        );
+        for accumulation in [Accumulation::Checked, Accumulation::ArbitraryPrecision] {
+            let result = run_mul_program_with(corrupted_program.clone(), true, accumulation);
+            assert_eq!(result, Ok(BigInt::from(48)));
+        }
+    }
+
+    #[test]
+    fn erase_disabled_code_works() {
+        let corrupted_program = String::from(
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))",
+        );
+        let preprocessed_program = erase_disabled_code(corrupted_program);
+        assert_eq!(
+            preprocessed_program,
+            "xmul(2,4)&mul[3,7]!^don't()do()?mul(8,5))"
+        );
+
+        let corrupted_program_complex = String::from(
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)do()+\nmul(32,64](mul(11,8)don't()undo()?mul(8,5))",
+        );
+        let preprocessed_program = erase_disabled_code(corrupted_program_complex);
+        assert_eq!(
+            preprocessed_program,
+            "xmul(2,4)&mul[3,7]!^don't()do()+\nmul(32,64](mul(11,8)don't()do()?mul(8,5))"
+        );
+
+        let corrupted_program_interleaved = String::from(
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+\ndon't()mul(32,64](mul(11,8)do()undo()?mul(8,5))",
+        );
+        let preprocessed_program = erase_disabled_code(corrupted_program_interleaved);
+        assert_eq!(
+            preprocessed_program,
+            "xmul(2,4)&mul[3,7]!^don't()do()undo()?mul(8,5))"
+        );
+    }
+
+    #[test]
+    fn run_mul_program_works() {
+        let corrupted_program =
+            String::from("xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))");
+        let result = run_mul_program(corrupted_program, false);
+        assert_eq!(result, 161);
+
+        let corrupted_program_multiline = String::from(
+            "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+\nmul(32,64]then(mul(11,8)mul(8,5))",
+        );
+        let result = run_mul_program(corrupted_program_multiline, false);
+        assert_eq!(result, 161);
+
+        let corrupted_program_conditionals = String::from(
+            "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))",
+        );
+        let result = run_mul_program(corrupted_program_conditionals, true);
+        assert_eq!(result, 48);
+    }
+}
@@ -1,57 +1,51 @@
-use std::{collections::HashSet, fs};
+#![allow(clippy::needless_return)]
 
-// use aoc24::similarity_score;
-// use aoc24::total_distance;
-// use aoc24::count_safe_reports;
-// use aoc24::run_mul_program;
+use std::{collections::HashSet, env, fs};
+
+// use aoc24::day01::{self, similarity_score, total_distance};
+// use aoc24::day02::count_safe_reports;
+// use aoc24::day03::run_mul_program;
 // use aoc24::wordsearch::{find_crossed_mas, parse_input_04};
 // use aoc24::printer::{
 //     add_middle_pages, generate_valid_update, parse_input_05, validate_update, Update,
 // };
+use aoc24::day02::{self, diagnose_report, SafetyPolicy};
+use aoc24::day03::{
+    format_trace_json_lines, format_trace_table, interpret, tokenize, trace, InstructionSet,
+};
 use aoc24::maps::{parse_input_06, walk};
 
-pub fn parse_input_01(contents: String) -> (Vec<usize>, Vec<usize>) {
-    let mut left: Vec<usize> = Vec::new();
-    let mut right: Vec<usize> = Vec::new();
-
-    let lines = contents.lines();
-    for line in lines {
-        let mut numbers = line.split_whitespace();
-        let left_number = numbers
-            .next()
-            .expect("Left value should not be empty")
-            .parse::<usize>()
-            .unwrap();
-        let right_number = numbers
-            .next()
-            .expect("Right value should not be empty")
-            .parse::<usize>()
-            .unwrap();
-
-        left.push(left_number);
-        right.push(right_number);
-    }
-
-    return (left, right);
-}
+fn list_unsafe_02() {
+    let contents = fs::read_to_string("input02.txt").expect("File 'input02.txt' should be present");
 
-pub fn parse_input_02(contents: String) -> Vec<Vec<isize>> {
-    let lines = contents.lines();
-    let mut reports: Vec<Vec<isize>> = Vec::new();
-    for line in lines {
-        let mut report: Vec<isize> = Vec::new();
-        for num_str in line.split_whitespace() {
-            report.push(num_str.parse::<isize>().unwrap());
+    let policy = SafetyPolicy::default();
+    for (index, report) in day02::parse(contents).iter().enumerate() {
+        let diagnostic = diagnose_report(report, &policy);
+        if !diagnostic.is_safe() {
+            println!("Report {}: {:?} is {}", index + 1, report, diagnostic);
         }
-        reports.push(report);
     }
+}
+
+fn trace_03(format: &str) {
+    let contents = fs::read_to_string("input03.txt").expect("File 'input03.txt' should be present");
 
-    return reports;
+    let instruction_set = InstructionSet::default();
+    let executions = interpret(
+        &tokenize(&contents, &instruction_set),
+        true,
+        &instruction_set,
+    )
+    .expect("Program should be balanced");
+    let entries = trace(&executions).expect("Total should not overflow");
+    match format {
+        "json" => print!("{}", format_trace_json_lines(&entries)),
+        _ => print!("{}", format_trace_table(&entries)),
+    }
 }
 
-fn main() {
-    let contents =
-        fs::read_to_string("input06.txt").expect("File 'input06.txt' should be present");
+fn solve_06() {
+    let contents = fs::read_to_string("input06.txt").expect("File 'input06.txt' should be present");
     println!("{contents}");
 
     let (map, start, initial_direction) = parse_input_06(contents);
@@ -61,3 +55,13 @@ fn main() {
     let result = path.iter().collect::<HashSet<_>>().len();
     println!("Result: {result}");
 }
+
+fn main() {
+    // Usage: aoc24 [unsafe02 | trace03 [table|json]]
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(|a| a.as_str()) {
+        Some("unsafe02") => list_unsafe_02(),
+        Some("trace03") => trace_03(args.get(2).map_or("table", |f| f.as_str())),
+        _ => solve_06(),
+    }
+}
@@ -51,9 +51,10 @@ fn turn_direction(direction: &Direction) -> Direction {
     return (-old_dy, old_dx);
 }
 
+#[allow(clippy::while_let_loop)]
 pub fn walk(map: Map, start: Point, initial_direction: Direction) -> Vec<Point> {
     let mut path: Vec<Point> = Vec::new();
-    let mut direction = initial_direction.clone();
+    let mut direction = initial_direction;
 
     path.push(start);
     loop {
@@ -103,7 +104,7 @@ pub fn parse_input_06(contents: String) -> (Map, Point, Direction) {
                 .collect::<Vec<Point>>(),
         );
 
-        if let Some((initial_x, direction)) = find_marker(&line) {
+        if let Some((initial_x, direction)) = find_marker(line) {
             if initial_direction != (0, 0) {
                 panic!("Direction was already set");
             }
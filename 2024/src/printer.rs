@@ -1,16 +1,30 @@
-use core::fmt::Debug;
+use core::cmp::Ordering;
+use core::fmt::{Debug, Display, Formatter};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write as _;
+use std::io::{self, Write as _};
+use std::process::{Command, Stdio};
 
-use petgraph::algo::{toposort, Cycle};
+use petgraph::algo::{is_cyclic_directed, tarjan_scc, toposort, Cycle};
 use petgraph::graphmap::DiGraphMap;
 use petgraph::visit::{
-    Data, DfsPostOrder, GraphBase, GraphProp, IntoNeighbors, IntoNeighborsDirected,
-    IntoNodeIdentifiers, Visitable,
+    Data, DfsPostOrder, GraphBase, GraphProp, IntoEdgeReferences, IntoNeighbors,
+    IntoNeighborsDirected, IntoNodeIdentifiers, NodeFiltered, Visitable,
 };
 use petgraph::Directed;
 
 pub type Page = usize;
 pub type Ruleset = DiGraphMap<Page, ()>;
 pub type Update = Vec<Page>;
+pub type RuleEdges = HashSet<(Page, Page)>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValidationStrategy {
+    // Depth-first search over the ruleset, sensitive to unrelated cycles
+    GraphTraversal,
+    // Direct lookup of every ordered pair of pages against the rules
+    Pairwise,
+}
 
 pub fn validate_update<G>(update: &Update, ruleset: G) -> bool
 where
@@ -19,9 +33,7 @@ where
 {
     // Graph might contain cycles, so we use post-order (backwards) depth-first search
     let mut visitor = DfsPostOrder::new(ruleset, update[0]);
-    let mut seq_iter = update.iter().rev();
-
-    while let Some(&sequence_node) = seq_iter.next() {
+    for &sequence_node in update.iter().rev() {
         loop {
             if let Some(visited_node) = visitor.next(ruleset) {
                 if sequence_node == visited_node {
@@ -37,12 +49,105 @@ where
     return true;
 }
 
+pub fn rule_edges(ruleset: &Ruleset) -> RuleEdges {
+    return ruleset
+        .all_edges()
+        .map(|(before, after, _)| (before, after))
+        .collect();
+}
+
+pub fn validate_update_pairwise(update: &Update, rule_edges: &RuleEdges) -> bool {
+    // An update is valid if no page has a rule requiring it before an earlier page
+    for (ii, &earlier) in update.iter().enumerate() {
+        for &later in update.iter().skip(ii + 1) {
+            if rule_edges.contains(&(later, earlier)) {
+                return false;
+            }
+        }
+    }
+    return true;
+}
+
+pub struct UpdateValidator<'a> {
+    ruleset: &'a Ruleset,
+    // Only needed by the pairwise strategy, and built once for all updates
+    rule_edges: Option<RuleEdges>,
+}
+
+impl<'a> UpdateValidator<'a> {
+    pub fn new(ruleset: &'a Ruleset, strategy: ValidationStrategy) -> UpdateValidator<'a> {
+        UpdateValidator {
+            ruleset,
+            rule_edges: match strategy {
+                ValidationStrategy::GraphTraversal => None,
+                ValidationStrategy::Pairwise => Some(rule_edges(ruleset)),
+            },
+        }
+    }
+
+    pub fn validate(&self, update: &Update) -> bool {
+        return match &self.rule_edges {
+            Some(rule_edges) => validate_update_pairwise(update, rule_edges),
+            None => validate_update(update, self.ruleset),
+        };
+    }
+}
+
+pub fn validate_update_with(
+    update: &Update,
+    ruleset: &Ruleset,
+    strategy: ValidationStrategy,
+) -> bool {
+    // Convenient for a single update, but to check many of them
+    // an UpdateValidator avoids collecting the rule edges every time
+    return UpdateValidator::new(ruleset, strategy).validate(update);
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RuleViolation {
+    // The rule `before|after` requires `before` to be printed first,
+    // but in the update it appears later
+    pub before: Page,
+    pub after: Page,
+    pub before_position: usize,
+    pub after_position: usize,
+}
+
+impl Display for RuleViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "page {} appears after {} but rule {}|{} requires it first",
+            self.before, self.after, self.before, self.after
+        )
+    }
+}
+
+pub fn validate_update_detailed(update: &Update, ruleset: &Ruleset) -> Vec<RuleViolation> {
+    // Every pair of pages printed in the wrong order is a violation,
+    // so we check them all instead of stopping at the first one
+    let mut violations: Vec<RuleViolation> = Vec::new();
+    for (after_position, &after) in update.iter().enumerate() {
+        for (before_position, &before) in update.iter().enumerate().skip(after_position + 1) {
+            if ruleset.contains_edge(before, after) {
+                violations.push(RuleViolation {
+                    before,
+                    after,
+                    before_position,
+                    after_position,
+                });
+            }
+        }
+    }
+    return violations;
+}
+
 pub fn add_middle_pages(updates: &Vec<Update>) -> Page {
     let mut middle_pages: Vec<Page> = Vec::new();
     for update in updates {
         middle_pages.push(update[update.len() / 2]);
     }
-    return middle_pages.iter().fold(0, |acc, p| acc + p);
+    return middle_pages.iter().sum();
 }
 
 pub fn generate_valid_update<G>(ruleset: G) -> Result<Vec<G::NodeId>, Cycle<G::NodeId>>
@@ -58,41 +163,479 @@ where
     return toposort(ruleset, None);
 }
 
-pub fn parse_input_05(contents: String) -> (DiGraphMap<Page, ()>, Vec<Update>) {
-    let mut lines = contents.lines();
+pub fn restrict_ruleset(update: &Update, ruleset: &Ruleset) -> Ruleset {
+    // Only the rules between pages of the update matter,
+    // the global ruleset might be cyclic even if these are not
+    let filtered_rules = NodeFiltered::from_fn(ruleset, |node| update.contains(&node));
+    let mut restricted_rules = Ruleset::new();
+    // Pages without any rules still belong to the update
+    for &page in update {
+        restricted_rules.add_node(page);
+    }
+    for edge in filtered_rules.edge_references() {
+        restricted_rules.add_edge(edge.0, edge.1, ());
+    }
+    return restricted_rules;
+}
+
+pub fn reorder_update(update: &Update, ruleset: &Ruleset) -> Result<Update, Cycle<Page>> {
+    let restricted_rules = restrict_ruleset(update, ruleset);
+    return generate_valid_update(&restricted_rules);
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RuleCycle {
+    // All the pages of the strongly connected component, sorted
+    pub pages: Vec<Page>,
+    // One of the shortest cycles inside the component, as a chain of rules
+    pub rules: Vec<(Page, Page)>,
+}
+
+pub fn strongly_connected_components(ruleset: &Ruleset) -> Vec<Vec<Page>> {
+    let mut components = tarjan_scc(ruleset);
+    for component in components.iter_mut() {
+        component.sort();
+    }
+    components.sort();
+    return components;
+}
+
+fn shortest_cycle_from(
+    start: Page,
+    component: &[Page],
+    ruleset: &Ruleset,
+) -> Option<Vec<(Page, Page)>> {
+    // Breadth-first search until we come back to the start page
+    let mut parents: HashMap<Page, Page> = HashMap::new();
+    let mut queue: VecDeque<Page> = VecDeque::from([start]);
+    while let Some(page) = queue.pop_front() {
+        for next_page in ruleset.neighbors(page) {
+            if next_page == start {
+                let mut rules = vec![(page, start)];
+                let mut current = page;
+                while current != start {
+                    let parent = parents[&current];
+                    rules.push((parent, current));
+                    current = parent;
+                }
+                rules.reverse();
+                return Some(rules);
+            }
+            if component.contains(&next_page) && !parents.contains_key(&next_page) {
+                parents.insert(next_page, page);
+                queue.push_back(next_page);
+            }
+        }
+    }
+    None
+}
+
+pub fn minimal_cycle(component: &[Page], ruleset: &Ruleset) -> Option<Vec<(Page, Page)>> {
+    let mut shortest: Option<Vec<(Page, Page)>> = None;
+    for &start in component {
+        if let Some(rules) = shortest_cycle_from(start, component, ruleset) {
+            if shortest.as_ref().is_none_or(|s| rules.len() < s.len()) {
+                shortest = Some(rules);
+            }
+        }
+    }
+    return shortest;
+}
+
+pub fn find_cycles(ruleset: &Ruleset) -> Vec<RuleCycle> {
+    // Single pages only form a cycle if they have a rule to themselves,
+    // which the search for a minimal cycle already takes into account
+    return strongly_connected_components(ruleset)
+        .into_iter()
+        .filter_map(|pages| minimal_cycle(&pages, ruleset).map(|rules| RuleCycle { pages, rules }))
+        .collect();
+}
+
+pub fn is_consistent_for_update(update: &Update, ruleset: &Ruleset) -> bool {
+    return !is_cyclic_directed(&restrict_ruleset(update, ruleset));
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RuleOrderingError {
+    // No rule relates these two pages
+    Incomparable(Page, Page),
+    // The rules between these pages form a cycle
+    Cyclic(Page, Page),
+}
+
+pub struct RuleOrdering {
+    rule_edges: RuleEdges,
+}
+
+impl RuleOrdering {
+    pub fn new(ruleset: &Ruleset) -> RuleOrdering {
+        RuleOrdering {
+            rule_edges: rule_edges(ruleset),
+        }
+    }
+
+    pub fn compare(&self, a: &Page, b: &Page) -> Ordering {
+        if a == b {
+            Ordering::Equal
+        } else if self.rule_edges.contains(&(*a, *b)) {
+            Ordering::Less
+        } else if self.rule_edges.contains(&(*b, *a)) {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    }
+
+    pub fn sort(&self, pages: &mut [Page]) -> Result<(), RuleOrderingError> {
+        // The comparator is only meaningful if the rules restricted to the pages
+        // are a total order, so every pair must be related...
+        for (ii, a) in pages.iter().enumerate() {
+            for b in pages.iter().skip(ii + 1) {
+                if (a != b) & (self.compare(a, b) == Ordering::Equal) {
+                    return Err(RuleOrderingError::Incomparable(*a, *b));
+                }
+            }
+        }
+        // ...and without cycles. Related pairs make a tournament, which is acyclic
+        // only if every page precedes a different number of the others
+        let mut preceded_by: HashMap<usize, Page> = HashMap::new();
+        for a in pages.iter() {
+            let num_following = pages
+                .iter()
+                .filter(|b| self.compare(a, b) == Ordering::Less)
+                .count();
+            if let Some(&b) = preceded_by.get(&num_following) {
+                if b != *a {
+                    return Err(RuleOrderingError::Cyclic(b, *a));
+                }
+            }
+            preceded_by.insert(num_following, *a);
+        }
+        pages.sort_by(|a, b| self.compare(a, b));
+        return Ok(());
+    }
+}
+
+pub struct RuleIndex {
+    ruleset: Ruleset,
+    // Pages reachable from each page, rebuilt lazily after removing rules
+    reachable: Option<HashMap<Page, HashSet<Page>>>,
+}
+
+impl RuleIndex {
+    pub fn new(ruleset: Ruleset) -> RuleIndex {
+        RuleIndex {
+            ruleset,
+            reachable: None,
+        }
+    }
+
+    pub fn ruleset(&self) -> &Ruleset {
+        &self.ruleset
+    }
+
+    pub fn add_rule(&mut self, before: Page, after: Page) {
+        self.ruleset.add_edge(before, after, ());
+        if let Some(reachable) = self.reachable.as_mut() {
+            // Whatever reaches `before` now also reaches `after` and beyond
+            let mut newly_reachable = reachable.get(&after).cloned().unwrap_or_default();
+            newly_reachable.insert(after);
+            reachable.entry(before).or_default();
+            reachable.entry(after).or_default();
+            for (&page, pages) in reachable.iter_mut() {
+                if (page == before) | pages.contains(&before) {
+                    pages.extend(&newly_reachable);
+                }
+            }
+        }
+    }
+
+    pub fn remove_rule(&mut self, before: Page, after: Page) -> bool {
+        let removed = self.ruleset.remove_edge(before, after).is_some();
+        if removed {
+            // There might be other paths left, so it's simpler to start over
+            self.reachable = None;
+        }
+        return removed;
+    }
+
+    pub fn warm_up(&mut self) {
+        if self.reachable.is_some() {
+            return;
+        }
+        let mut reachable: HashMap<Page, HashSet<Page>> = HashMap::new();
+        for page in self.ruleset.nodes() {
+            // Start from the successors, so that a page only reaches itself through a cycle
+            let mut pages: HashSet<Page> = HashSet::new();
+            let mut stack: Vec<Page> = self.ruleset.neighbors(page).collect();
+            while let Some(next_page) = stack.pop() {
+                if pages.insert(next_page) {
+                    stack.extend(self.ruleset.neighbors(next_page));
+                }
+            }
+            reachable.insert(page, pages);
+        }
+        self.reachable = Some(reachable);
+    }
+
+    pub fn must_precede(&mut self, a: Page, b: Page) -> bool {
+        self.warm_up();
+        return self
+            .reachable
+            .as_ref()
+            .unwrap()
+            .get(&a)
+            .is_some_and(|pages| pages.contains(&b));
+    }
+}
+
+pub fn has_unique_ordering(update: &Update, ruleset: &Ruleset) -> bool {
+    // A topological order is the only one if every consecutive pair is ruled,
+    // otherwise those two pages could be swapped
+    let restricted_rules = restrict_ruleset(update, ruleset);
+    if let Ok(ordering) = generate_valid_update(&restricted_rules) {
+        return ordering
+            .windows(2)
+            .all(|pair| restricted_rules.contains_edge(pair[0], pair[1]));
+    }
+    return false;
+}
+
+// Counting goes through every subset of pages, so it is only feasible for short updates
+pub const MAX_COUNTED_PAGES: usize = 20;
+
+pub fn count_valid_orderings(update: &Update, ruleset: &Ruleset) -> Option<u64> {
+    let pages = update;
+    if pages.len() > MAX_COUNTED_PAGES {
+        return None;
+    }
+    let predecessors: Vec<usize> = pages
+        .iter()
+        .map(|&page| {
+            pages
+                .iter()
+                .enumerate()
+                .filter(|(_, &other)| other != page && ruleset.contains_edge(other, page))
+                .fold(0, |mask, (jj, _)| mask | (1 << jj))
+        })
+        .collect();
+
+    // Number of ways of printing each subset of pages first
+    let mut ways: Vec<u64> = vec![0; 1 << pages.len()];
+    ways[0] = 1;
+    for printed in 0..ways.len() {
+        if ways[printed] == 0 {
+            continue;
+        }
+        for (ii, &preds) in predecessors.iter().enumerate() {
+            if (printed & (1 << ii) == 0) & (preds & !printed == 0) {
+                ways[printed | (1 << ii)] += ways[printed];
+            }
+        }
+    }
+    return Some(ways[ways.len() - 1]);
+}
+
+pub struct ValidOrderings {
+    pages: Vec<Page>,
+    predecessors: Vec<Vec<usize>>,
+    printed: Vec<bool>,
+    // Indices of the pages chosen so far, in printing order
+    chosen: Vec<usize>,
+    started: bool,
+}
+
+impl ValidOrderings {
+    pub fn new(update: &Update, ruleset: &Ruleset) -> ValidOrderings {
+        let mut pages = update.clone();
+        pages.sort();
+        let predecessors = pages
+            .iter()
+            .map(|&page| {
+                (0..pages.len())
+                    .filter(|&jj| pages[jj] != page && ruleset.contains_edge(pages[jj], page))
+                    .collect()
+            })
+            .collect();
+        // With cyclic rules there is nothing to enumerate
+        let started = !is_consistent_for_update(update, ruleset);
+        ValidOrderings {
+            printed: vec![false; pages.len()],
+            chosen: Vec::new(),
+            pages,
+            predecessors,
+            started,
+        }
+    }
+
+    fn next_available(&self, from: usize) -> Option<usize> {
+        return (from..self.pages.len()).find(|&ii| {
+            !self.printed[ii] && self.predecessors[ii].iter().all(|&jj| self.printed[jj])
+        });
+    }
+
+    fn choose(&mut self, ii: usize) {
+        self.printed[ii] = true;
+        self.chosen.push(ii);
+    }
+
+    fn complete(&mut self) -> Update {
+        // The rules are acyclic, so there is always some page available
+        while self.chosen.len() < self.pages.len() {
+            let ii = self.next_available(0).unwrap();
+            self.choose(ii);
+        }
+        return self.chosen.iter().map(|&ii| self.pages[ii]).collect();
+    }
+}
+
+impl Iterator for ValidOrderings {
+    type Item = Update;
+
+    fn next(&mut self) -> Option<Update> {
+        if !self.started {
+            self.started = true;
+            return Some(self.complete());
+        }
+        // Backtrack until some earlier choice has an alternative
+        while let Some(last) = self.chosen.pop() {
+            self.printed[last] = false;
+            if let Some(ii) = self.next_available(last + 1) {
+                self.choose(ii);
+                return Some(self.complete());
+            }
+        }
+        None
+    }
+}
+
+pub fn ruleset_to_dot(ruleset: &Ruleset, update: Option<&Update>) -> String {
+    let (rules, violations) = match update {
+        Some(update) => (
+            restrict_ruleset(update, ruleset),
+            validate_update_detailed(update, ruleset)
+                .iter()
+                .map(|v| (v.before, v.after))
+                .collect::<HashSet<(Page, Page)>>(),
+        ),
+        None => (ruleset.clone(), HashSet::new()),
+    };
+    let cycle_members: HashSet<Page> = find_cycles(&rules)
+        .into_iter()
+        .flat_map(|cycle| cycle.pages)
+        .collect();
+
+    // Sort everything so that the output is stable
+    let mut pages: Vec<Page> = rules.nodes().collect();
+    pages.sort();
+    let mut edges: Vec<(Page, Page)> = rules.all_edges().map(|(b, a, _)| (b, a)).collect();
+    edges.sort();
+
+    let mut dot = String::from("digraph ruleset {\n");
+    for page in pages {
+        if cycle_members.contains(&page) {
+            writeln!(dot, "    {page} [style=filled, fillcolor=orange];").unwrap();
+        } else {
+            writeln!(dot, "    {page};").unwrap();
+        }
+    }
+    for (before, after) in edges {
+        if violations.contains(&(before, after)) {
+            writeln!(dot, "    {before} -> {after} [color=red, penwidth=2];").unwrap();
+        } else {
+            writeln!(dot, "    {before} -> {after};").unwrap();
+        }
+    }
+    dot.push_str("}\n");
+    return dot;
+}
+
+pub fn render_dot(dot: &str, format: &str) -> io::Result<Vec<u8>> {
+    // Relies on a local Graphviz installation, fails if `dot` is not in the PATH
+    let mut child = Command::new("dot")
+        .arg(format!("-T{format}"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(dot.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+    return Ok(output.stdout);
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    MissingSeparator,
+    InvalidRule { line: usize },
+    InvalidPage { line: usize, value: String },
+    EmptyUpdate { line: usize },
+    EvenUpdate { line: usize },
+}
+
+fn parse_page(value: &str, line: usize) -> Result<Page, ParseError> {
+    return value
+        .trim()
+        .parse::<Page>()
+        .map_err(|_| ParseError::InvalidPage {
+            line,
+            value: value.to_string(),
+        });
+}
+
+pub fn parse_input_05(contents: String) -> Result<(Ruleset, Vec<Update>), ParseError> {
+    // Line numbers are 1-based, and `lines` already takes care of CRLF endings
+    let lines: Vec<(&str, usize)> = contents.lines().map(|l| l.trim()).zip(1..).collect();
+    // Leading blank lines are not the separator, so they are skipped
+    let first = lines
+        .iter()
+        .position(|(line, _)| !line.is_empty())
+        .unwrap_or(lines.len());
+    let lines = &lines[first..];
+    let Some(separator) = lines.iter().position(|(line, _)| line.is_empty()) else {
+        return Err(ParseError::MissingSeparator);
+    };
     let mut rules: Vec<(Page, Page)> = Vec::new();
     let mut updates: Vec<Update> = Vec::new();
-    loop {
-        let line = lines.next().unwrap();
-        if line == "" {
-            break;
-        }
-        let rule_strs = line.split_once("|").unwrap();
-        rules.push((
-            rule_strs.0.parse::<Page>().unwrap(),
-            rule_strs.1.parse::<Page>().unwrap(),
-        ));
+    for &(line, line_no) in &lines[..separator] {
+        let Some((before, after)) = line.split_once("|") else {
+            return Err(ParseError::InvalidRule { line: line_no });
+        };
+        rules.push((parse_page(before, line_no)?, parse_page(after, line_no)?));
     }
-    loop {
-        if let Some(line) = lines.next() {
-            let update: Update = line
-                .split(",")
-                .map(|s| s.parse::<Page>().unwrap())
-                .collect();
-            updates.push(update);
-        } else {
-            break;
+
+    let update_lines = &lines[separator + 1..];
+    // Trailing blank lines are fine, but not blank updates in between
+    let num_updates = update_lines
+        .iter()
+        .rposition(|(line, _)| !line.is_empty())
+        .map_or(0, |last| last + 1);
+    for &(line, line_no) in &update_lines[..num_updates] {
+        if line.is_empty() {
+            return Err(ParseError::EmptyUpdate { line: line_no });
         }
+        let update = line
+            .split(",")
+            .map(|s| parse_page(s, line_no))
+            .collect::<Result<Update, ParseError>>()?;
+        // Otherwise there is no middle page
+        if update.len() % 2 == 0 {
+            return Err(ParseError::EvenUpdate { line: line_no });
+        }
+        updates.push(update);
     }
     let rule_dag = DiGraphMap::from_edges(rules);
-    return (rule_dag, updates);
+    return Ok((rule_dag, updates));
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
-    use petgraph::visit::{IntoEdgeReferences, NodeFiltered};
+    const EXAMPLE_05: &str = include_str!("../fixtures/day05/example.txt");
 
     #[test]
     fn validate_update_works() {
@@ -174,6 +717,399 @@ mod test {
         assert!(validate_update(&valid_update, &filtered_rules));
     }
 
+    #[test]
+    fn validate_update_detailed_works() {
+        let rules = Ruleset::from_edges(&[(47, 53), (75, 53), (47, 13), (75, 47), (75, 13)]);
+        let valid_update = Update::from_iter([75, 47, 13]);
+        let invalid_update = Update::from_iter([47, 13, 75]);
+
+        assert_eq!(validate_update_detailed(&valid_update, &rules), vec![]);
+
+        let violations = validate_update_detailed(&invalid_update, &rules);
+        assert_eq!(
+            violations,
+            vec![
+                RuleViolation {
+                    before: 75,
+                    after: 47,
+                    before_position: 2,
+                    after_position: 0,
+                },
+                RuleViolation {
+                    before: 75,
+                    after: 13,
+                    before_position: 2,
+                    after_position: 1,
+                },
+            ]
+        );
+        assert_eq!(
+            violations[1].to_string(),
+            "page 75 appears after 13 but rule 75|13 requires it first"
+        );
+    }
+
+    #[test]
+    fn reorder_update_works() {
+        let (rules, _) = parse_input_05(EXAMPLE_05.to_string()).unwrap();
+
+        assert_eq!(
+            reorder_update(&vec![75, 97, 47, 61, 53], &rules).unwrap(),
+            vec![97, 75, 47, 61, 53]
+        );
+        assert_eq!(
+            reorder_update(&vec![61, 13, 29], &rules).unwrap(),
+            vec![61, 29, 13]
+        );
+        assert_eq!(
+            reorder_update(&vec![97, 13, 75, 29, 47], &rules).unwrap(),
+            vec![97, 75, 47, 29, 13]
+        );
+    }
+
+    #[test]
+    fn reorder_update_with_wicked_cycle_works() {
+        let rules = Ruleset::from_edges([(29, 16), (52, 16), (16, 47), (47, 29), (52, 47)]);
+
+        // The global ruleset cannot be sorted, but the restricted one can
+        assert!(generate_valid_update(&rules).is_err());
+        assert_eq!(
+            reorder_update(&vec![47, 16, 52], &rules).unwrap(),
+            vec![52, 16, 47]
+        );
+    }
+
+    #[test]
+    fn reorder_invalid_updates_works() {
+        let (rules, updates) = parse_input_05(EXAMPLE_05.to_string()).unwrap();
+        let reordered_updates: Vec<Update> = updates
+            .iter()
+            .filter(|update| !validate_update(update, &restrict_ruleset(update, &rules)))
+            .map(|update| reorder_update(update, &rules).unwrap())
+            .collect();
+
+        assert_eq!(add_middle_pages(&reordered_updates), 123);
+    }
+
+    #[test]
+    fn validate_update_pairwise_works() {
+        let (rules, updates) = parse_input_05(EXAMPLE_05.to_string()).unwrap();
+        let edges = rule_edges(&rules);
+        let validity: Vec<bool> = updates
+            .iter()
+            .map(|update| validate_update_pairwise(update, &edges))
+            .collect();
+
+        assert_eq!(validity, [true, true, true, false, false, false]);
+    }
+
+    #[test]
+    fn update_validator_works() {
+        let (rules, updates) = parse_input_05(EXAMPLE_05.to_string()).unwrap();
+        for strategy in [
+            ValidationStrategy::GraphTraversal,
+            ValidationStrategy::Pairwise,
+        ] {
+            let validator = UpdateValidator::new(&rules, strategy);
+            let validity: Vec<bool> = updates.iter().map(|u| validator.validate(u)).collect();
+            assert_eq!(validity, [true, true, true, false, false, false]);
+        }
+    }
+
+    #[test]
+    fn validate_update_strategies_agree() {
+        let (rules, updates) = parse_input_05(EXAMPLE_05.to_string()).unwrap();
+        let circular_rules = Ruleset::from_edges(&[(47, 75), (75, 13), (13, 53), (53, 47)]);
+        let circular_updates: Vec<Update> =
+            vec![vec![53, 47, 75], vec![75, 13, 53], vec![53, 75, 47]];
+
+        for (rules, updates) in [(rules, updates), (circular_rules, circular_updates)] {
+            for update in updates.iter() {
+                assert_eq!(
+                    validate_update_with(update, &rules, ValidationStrategy::GraphTraversal),
+                    validate_update_with(update, &rules, ValidationStrategy::Pairwise),
+                    "Strategies disagree on {:?}",
+                    update
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn validate_update_pairwise_with_wicked_cycle_works() {
+        let rules = Ruleset::from_edges([(29, 16), (52, 16), (16, 47), (47, 29), (52, 47)]);
+        let valid_update = Update::from_iter([52, 16, 47]);
+
+        // Unlike the graph traversal, unrelated cycles do not matter here
+        assert!(!validate_update_with(
+            &valid_update,
+            &rules,
+            ValidationStrategy::GraphTraversal
+        ));
+        assert!(validate_update_with(
+            &valid_update,
+            &rules,
+            ValidationStrategy::Pairwise
+        ));
+
+        // Both agree once the rules are restricted to the pages of the update
+        let restricted_rules = restrict_ruleset(&valid_update, &rules);
+        assert!(validate_update_with(
+            &valid_update,
+            &restricted_rules,
+            ValidationStrategy::GraphTraversal
+        ));
+    }
+
+    #[test]
+    fn find_cycles_works() {
+        let rules = Ruleset::from_edges(&[
+            (47, 75),
+            (75, 13),
+            (13, 53),
+            (53, 47),
+            (47, 13), // Shortcut, makes a shorter cycle
+            (53, 61),
+            (61, 61), // Self-loop
+            (29, 61),
+        ]);
+
+        assert_eq!(
+            strongly_connected_components(&rules),
+            vec![vec![13, 47, 53, 75], vec![29], vec![61]]
+        );
+        assert_eq!(
+            find_cycles(&rules),
+            vec![
+                RuleCycle {
+                    pages: vec![13, 47, 53, 75],
+                    rules: vec![(13, 53), (53, 47), (47, 13)],
+                },
+                RuleCycle {
+                    pages: vec![61],
+                    rules: vec![(61, 61)],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn find_cycles_without_cycles_works() {
+        let (rules, _) = parse_input_05(EXAMPLE_05.to_string()).unwrap();
+        assert_eq!(find_cycles(&rules), vec![]);
+    }
+
+    #[test]
+    fn is_consistent_for_update_works() {
+        let rules = Ruleset::from_edges([(29, 16), (52, 16), (16, 47), (47, 29), (52, 47)]);
+
+        assert!(is_consistent_for_update(&vec![52, 16, 47], &rules));
+        assert!(!is_consistent_for_update(&vec![29, 16, 47], &rules));
+    }
+
+    #[test]
+    fn valid_orderings_works() {
+        let rules = Ruleset::from_edges(&[(47, 13), (75, 13), (13, 53)]);
+        let update = Update::from_iter([53, 13, 75, 47]);
+
+        assert!(!has_unique_ordering(&update, &rules));
+        assert_eq!(count_valid_orderings(&update, &rules), Some(2));
+        assert_eq!(
+            ValidOrderings::new(&update, &rules).collect::<Vec<Update>>(),
+            vec![vec![47, 75, 13, 53], vec![75, 47, 13, 53]]
+        );
+    }
+
+    #[test]
+    fn valid_orderings_without_rules_works() {
+        let rules = Ruleset::new();
+        let update = Update::from_iter([3, 1, 2]);
+
+        assert_eq!(count_valid_orderings(&update, &rules), Some(6));
+        assert_eq!(ValidOrderings::new(&update, &rules).count(), 6);
+        assert_eq!(
+            count_valid_orderings(&(0..=MAX_COUNTED_PAGES).collect(), &rules),
+            None
+        );
+    }
+
+    #[test]
+    fn valid_orderings_with_cyclic_rules_works() {
+        let rules = Ruleset::from_edges(&[(47, 75), (75, 13), (13, 47)]);
+        let update = Update::from_iter([47, 75, 13]);
+
+        assert!(!has_unique_ordering(&update, &rules));
+        assert_eq!(count_valid_orderings(&update, &rules), Some(0));
+        assert_eq!(ValidOrderings::new(&update, &rules).next(), None);
+    }
+
+    #[test]
+    fn valid_orderings_of_example_are_unique() {
+        let (rules, updates) = parse_input_05(EXAMPLE_05.to_string()).unwrap();
+        for update in updates.iter() {
+            assert!(has_unique_ordering(update, &rules));
+            assert_eq!(count_valid_orderings(update, &rules), Some(1));
+            assert_eq!(
+                ValidOrderings::new(update, &rules).collect::<Vec<Update>>(),
+                vec![reorder_update(update, &rules).unwrap()]
+            );
+        }
+    }
+
+    #[test]
+    fn rule_ordering_works() {
+        let (rules, updates) = parse_input_05(EXAMPLE_05.to_string()).unwrap();
+        let ordering = RuleOrdering::new(&rules);
+
+        assert_eq!(ordering.compare(&47, &53), Ordering::Less);
+        assert_eq!(ordering.compare(&53, &47), Ordering::Greater);
+        assert_eq!(ordering.compare(&53, &53), Ordering::Equal);
+
+        for update in updates.iter() {
+            let mut pages = update.clone();
+            ordering.sort(&mut pages).unwrap();
+            assert_eq!(pages, reorder_update(update, &rules).unwrap());
+        }
+    }
+
+    #[test]
+    fn rule_ordering_not_total_fails() {
+        let rules = Ruleset::from_edges(&[(47, 75), (75, 13), (13, 47), (47, 53)]);
+        let ordering = RuleOrdering::new(&rules);
+
+        assert_eq!(
+            ordering.sort(&mut [53, 75, 47]),
+            Err(RuleOrderingError::Incomparable(53, 75))
+        );
+        assert_eq!(
+            ordering.sort(&mut [13, 75, 47]),
+            Err(RuleOrderingError::Cyclic(13, 75))
+        );
+    }
+
+    #[test]
+    fn rule_index_works() {
+        let mut index = RuleIndex::new(Ruleset::from_edges(&[(47, 53), (53, 29)]));
+
+        assert!(index.must_precede(47, 29));
+        assert!(!index.must_precede(29, 47));
+        assert!(!index.must_precede(47, 47));
+        assert!(!index.must_precede(47, 13));
+
+        // Added rules update the warm cache in place
+        index.add_rule(29, 13);
+        assert!(index.must_precede(47, 13));
+        index.add_rule(13, 47);
+        assert!(index.must_precede(29, 53));
+        assert!(index.must_precede(47, 47));
+
+        assert!(index.remove_rule(13, 47));
+        assert!(!index.remove_rule(13, 47));
+        assert!(!index.must_precede(29, 53));
+        assert!(index.must_precede(47, 13));
+        assert_eq!(index.ruleset().edge_count(), 3);
+    }
+
+    #[test]
+    fn rule_index_add_rule_matches_rebuild() {
+        let (rules, _) = parse_input_05(EXAMPLE_05.to_string()).unwrap();
+        let mut incremental_index = RuleIndex::new(Ruleset::new());
+        incremental_index.warm_up();
+        for (before, after, _) in rules.all_edges() {
+            incremental_index.add_rule(before, after);
+        }
+        let mut rebuilt_index = RuleIndex::new(rules.clone());
+
+        for a in rules.nodes() {
+            for b in rules.nodes() {
+                assert_eq!(
+                    incremental_index.must_precede(a, b),
+                    rebuilt_index.must_precede(a, b)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ruleset_to_dot_works() {
+        let rules = Ruleset::from_edges(&[(47, 53), (75, 47), (53, 75)]);
+        assert_eq!(
+            ruleset_to_dot(&rules, None),
+            "digraph ruleset {
+    47 [style=filled, fillcolor=orange];
+    53 [style=filled, fillcolor=orange];
+    75 [style=filled, fillcolor=orange];
+    47 -> 53;
+    53 -> 75;
+    75 -> 47;
+}
+"
+        );
+    }
+
+    #[test]
+    fn ruleset_to_dot_with_update_works() {
+        let rules = Ruleset::from_edges(&[(47, 53), (75, 53), (47, 13), (75, 47), (75, 13)]);
+        let invalid_update = Update::from_iter([47, 13, 75]);
+        assert_eq!(
+            ruleset_to_dot(&rules, Some(&invalid_update)),
+            "digraph ruleset {
+    13;
+    47;
+    75;
+    47 -> 13;
+    75 -> 13 [color=red, penwidth=2];
+    75 -> 47 [color=red, penwidth=2];
+}
+"
+        );
+    }
+
+    #[test]
+    fn parse_input_05_works() {
+        let (rules, updates) = parse_input_05(EXAMPLE_05.to_string()).unwrap();
+        assert_eq!(rules.edge_count(), 21);
+        assert_eq!(updates.len(), 6);
+        assert_eq!(updates[0], vec![75, 47, 61, 53, 29]);
+
+        // Same contents with CRLF endings, stray whitespace and trailing blank lines
+        let messy_input = EXAMPLE_05.replace("\n", " \r\n") + "\r\n\r\n";
+        let (messy_rules, messy_updates) = parse_input_05(messy_input).unwrap();
+        assert_eq!(rule_edges(&messy_rules), rule_edges(&rules));
+        assert_eq!(messy_updates, updates);
+
+        let (rules, updates) = parse_input_05("\n\r\n47|53\n\n1\n".to_string()).unwrap();
+        assert_eq!(rule_edges(&rules), RuleEdges::from([(47, 53)]));
+        assert_eq!(updates, vec![vec![1]]);
+    }
+
+    #[test]
+    fn parse_input_05_errors() {
+        assert_eq!(
+            parse_input_05("47|53\n75,47,61\n".to_string()).unwrap_err(),
+            ParseError::MissingSeparator
+        );
+        assert_eq!(
+            parse_input_05("47|53\n47-61\n\n75,47,61\n".to_string()).unwrap_err(),
+            ParseError::InvalidRule { line: 2 }
+        );
+        assert_eq!(
+            parse_input_05("47|53\n\n75,4x,61\n".to_string()).unwrap_err(),
+            ParseError::InvalidPage {
+                line: 3,
+                value: "4x".to_string()
+            }
+        );
+        assert_eq!(
+            parse_input_05("47|53\n\n75,47,61\n\n75,29,13\n".to_string()).unwrap_err(),
+            ParseError::EmptyUpdate { line: 4 }
+        );
+        assert_eq!(
+            parse_input_05("47|53\n\n75,47\n".to_string()).unwrap_err(),
+            ParseError::EvenUpdate { line: 3 }
+        );
+    }
+
     #[test]
     fn add_middle_pages_works() {
         let updates: Vec<Update> = vec![
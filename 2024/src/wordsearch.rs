@@ -68,12 +68,13 @@ pub fn find_word_all_directions(table: &Table, word: &str) -> usize {
     for col in table.columns() {
         total_count += find_word_slice_2way(&col.to_vec(), word);
     }
-    for diag in iter_diagonals(&table) {
+    for diag in iter_diagonals(table) {
         total_count += find_word_slice_2way(&diag, word);
     }
     return total_count;
 }
 
+#[allow(clippy::collapsible_if)]
 pub fn find_crossed_mas(table: &Table) -> usize {
     let mut count = 0;
     for ii in 1..(table.nrows() - 1) {
@@ -108,6 +109,8 @@ pub fn parse_input_04(contents: String) -> Table {
 mod test {
     use super::*;
 
+    const EXAMPLE_04: &str = include_str!("../fixtures/day04/example.txt");
+
     #[test]
     fn find_word_slice_works() {
         let simple_slice: Vec<String> = vec!["X", "M", "A", "S", "X", "M", "A", "S"]
@@ -146,58 +149,14 @@ mod test {
 
     #[test]
     fn find_word_all_directions_works() {
-        // MMMSXXMASM
-        // MSAMXMSMSA
-        // AMXSXMAAMM
-        // MSAMASMSMX
-        // XMASAMXAMM
-        // XXAMMXXAMA
-        // SMSMSASXSS
-        // SAXAMASAAA
-        // MAMMMXMMMM
-        // MXMXAXMASX
-        let table = array![
-            ["M", "M", "M", "S", "X", "X", "M", "A", "S", "M"],
-            ["M", "S", "A", "M", "X", "M", "S", "M", "S", "A"],
-            ["A", "M", "X", "S", "X", "M", "A", "A", "M", "M"],
-            ["M", "S", "A", "M", "A", "S", "M", "S", "M", "X"],
-            ["X", "M", "A", "S", "A", "M", "X", "A", "M", "M"],
-            ["X", "X", "A", "M", "M", "X", "X", "A", "M", "A"],
-            ["S", "M", "S", "M", "S", "A", "S", "X", "S", "S"],
-            ["S", "A", "X", "A", "M", "A", "S", "A", "A", "A"],
-            ["M", "A", "M", "M", "M", "X", "M", "M", "M", "M"],
-            ["M", "X", "M", "X", "A", "X", "M", "A", "S", "X"],
-        ]
-        .map(|&s| s.to_string());
+        let table = parse_input_04(EXAMPLE_04.to_string());
         let result = find_word_all_directions(&table, "XMAS");
         assert_eq!(result, 18);
     }
 
     #[test]
     fn find_crossed_mas_works() {
-        // MMMSXXMASM
-        // MSAMXMSMSA
-        // AMXSXMAAMM
-        // MSAMASMSMX
-        // XMASAMXAMM
-        // XXAMMXXAMA
-        // SMSMSASXSS
-        // SAXAMASAAA
-        // MAMMMXMMMM
-        // MXMXAXMASX
-        let table = array![
-            ["M", "M", "M", "S", "X", "X", "M", "A", "S", "M"],
-            ["M", "S", "A", "M", "X", "M", "S", "M", "S", "A"],
-            ["A", "M", "X", "S", "X", "M", "A", "A", "M", "M"],
-            ["M", "S", "A", "M", "A", "S", "M", "S", "M", "X"],
-            ["X", "M", "A", "S", "A", "M", "X", "A", "M", "M"],
-            ["X", "X", "A", "M", "M", "X", "X", "A", "M", "A"],
-            ["S", "M", "S", "M", "S", "A", "S", "X", "S", "S"],
-            ["S", "A", "X", "A", "M", "A", "S", "A", "A", "A"],
-            ["M", "A", "M", "M", "M", "X", "M", "M", "M", "M"],
-            ["M", "X", "M", "X", "A", "X", "M", "A", "S", "X"],
-        ]
-        .map(|&s| s.to_string());
+        let table = parse_input_04(EXAMPLE_04.to_string());
         let result = find_crossed_mas(&table);
         assert_eq!(result, 9);
     }
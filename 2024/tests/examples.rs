@@ -0,0 +1,151 @@
+#![allow(clippy::needless_return)]
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use aoc24::day02::SafetyPolicy;
+use aoc24::maps::{parse_input_06, walk};
+use aoc24::printer::{
+    add_middle_pages, parse_input_05, reorder_update, UpdateValidator, ValidationStrategy,
+};
+use aoc24::wordsearch::{find_crossed_mas, find_word_all_directions, parse_input_04};
+use aoc24::{day01, day02, day03};
+
+struct Case {
+    file: String,
+    part: u8,
+    answer: String,
+}
+
+fn fixtures_dir() -> PathBuf {
+    return Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+}
+
+fn read_manifest() -> Vec<Case> {
+    let contents = fs::read_to_string(fixtures_dir().join("answers"))
+        .expect("File 'fixtures/answers' should be present");
+    let mut cases: Vec<Case> = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if let [file, part, answer] = fields[..] {
+            cases.push(Case {
+                file: file.to_string(),
+                part: part.parse().expect("Part should be a number"),
+                answer: answer.to_string(),
+            });
+        } else {
+            panic!("Manifest line should have a file, a part and an answer: {line}");
+        }
+    }
+    return cases;
+}
+
+// Every registered day, with the parser and solver for each part
+fn solve(day: &str, part: u8, contents: String) -> Option<String> {
+    let answer = match (day, part) {
+        ("day01", 1) => {
+            let (left, right) = day01::parse(contents);
+            day01::total_distance(&left, &right).to_string()
+        }
+        ("day01", 2) => {
+            let (left, right) = day01::parse(contents);
+            day01::similarity_score(&left, &right)
+                .expect("Score should not overflow")
+                .to_string()
+        }
+        ("day02", 1 | 2) => {
+            let reports = day02::parse(contents);
+            day02::count_safe_reports(&reports, part == 2, &SafetyPolicy::default()).to_string()
+        }
+        ("day03", 1 | 2) => {
+            let instructions = day03::parse(contents);
+            day03::run_instructions(&instructions, part == 2)
+                .expect("Program should run")
+                .to_string()
+        }
+        ("day04", 1) => find_word_all_directions(&parse_input_04(contents), "XMAS").to_string(),
+        ("day04", 2) => find_crossed_mas(&parse_input_04(contents)).to_string(),
+        ("day05", 1 | 2) => {
+            let (rules, updates) = parse_input_05(contents).expect("Input should be valid");
+            let validator = UpdateValidator::new(&rules, ValidationStrategy::Pairwise);
+            let (valid, invalid): (Vec<_>, Vec<_>) =
+                updates.into_iter().partition(|u| validator.validate(u));
+            if part == 1 {
+                add_middle_pages(&valid).to_string()
+            } else {
+                let reordered = invalid
+                    .iter()
+                    .map(|u| reorder_update(u, &rules).expect("Rules should be acyclic"))
+                    .collect();
+                add_middle_pages(&reordered).to_string()
+            }
+        }
+        ("day06", 1) => {
+            let (map, start, initial_direction) = parse_input_06(contents);
+            let path = walk(map, start, initial_direction);
+            path.iter().collect::<HashSet<_>>().len().to_string()
+        }
+        _ => return None,
+    };
+    return Some(answer);
+}
+
+#[test]
+fn examples_match_expected_answers() {
+    let cases = read_manifest();
+    assert!(!cases.is_empty(), "Manifest should list at least one case");
+
+    let mut failures: Vec<String> = Vec::new();
+    for case in &cases {
+        let day = case.file.split('/').next().unwrap();
+        let contents = fs::read_to_string(fixtures_dir().join(&case.file))
+            .unwrap_or_else(|_| panic!("File 'fixtures/{}' should be present", case.file));
+        match solve(day, case.part, contents) {
+            Some(answer) if answer == case.answer => {}
+            Some(answer) => failures.push(format!(
+                "{} part {}: expected {}, got {}",
+                case.file, case.part, case.answer, answer
+            )),
+            None => failures.push(format!(
+                "{} part {}: no solver registered",
+                case.file, case.part
+            )),
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn every_example_has_an_answer() {
+    let listed: HashSet<String> = read_manifest().into_iter().map(|c| c.file).collect();
+
+    let mut missing: Vec<String> = Vec::new();
+    for day in fs::read_dir(fixtures_dir()).unwrap() {
+        let day = day.unwrap().path();
+        if !day.is_dir() {
+            continue;
+        }
+        for example in fs::read_dir(&day).unwrap() {
+            let example = example.unwrap().path();
+            let name = example.file_name().unwrap().to_string_lossy().to_string();
+            if !(name.starts_with("example") && name.ends_with(".txt")) {
+                continue;
+            }
+            let file = format!("{}/{}", day.file_name().unwrap().to_string_lossy(), name);
+            if !listed.contains(&file) {
+                missing.push(file);
+            }
+        }
+    }
+    missing.sort();
+    assert!(
+        missing.is_empty(),
+        "Examples without answers: {}",
+        missing.join(", ")
+    );
+}